@@ -1,3 +1,6 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
 use rbotlib::*;
 
 fn main() {
@@ -9,7 +12,15 @@ fn main() {
     let joystick_port = driverstation::JoystickPort::new(0).unwrap();
     let joystick_axis = driverstation::JoystickAxis::new(1).unwrap();
 
-    loop {
+    let (tick_sender, ticks) = mpsc::channel();
+    let mut notifier = notifier::Notifier::new(move || {
+        let _ = tick_sender.send(());
+    })
+    .unwrap();
+    notifier.set_name("MainLoop").unwrap();
+    notifier.start_periodic(Duration::from_millis(20)).unwrap();
+
+    for _ in ticks.iter() {
         println!("joystick: {}", ds.get_stick_axis(joystick_port, joystick_axis).unwrap());
         println!("time: {}", fpga::get_time_us().unwrap());
     }
@@ -7,6 +7,15 @@ use std::fmt;
 
 use crate::*;
 
+// Status codes from `hal/Errors.h` that bindgen skips because they lack the `HAL_` prefix.
+pub const INCOMPATIBLE_STATE: i32 = 1015;
+pub const ANALOG_TRIGGER_LIMIT_ORDER_ERROR: i32 = -1010;
+pub const ANALOG_TRIGGER_PULSE_OUTPUT_ERROR: i32 = -1011;
+pub const NO_AVAILABLE_RESOURCES: i32 = -1004;
+pub const PARAMETER_OUT_OF_RANGE: i32 = -1028;
+pub const RESOURCE_IS_ALLOCATED: i32 = -1029;
+pub const RESOURCE_OUT_OF_RANGE: i32 = -1030;
+
 #[derive(Copy, Clone)]
 pub struct HalError(pub i32);
 
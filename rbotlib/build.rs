@@ -6,7 +6,11 @@ fn rbot_dir() -> PathBuf {
 }
 
 fn main() {
-    link_libs();
+    // The HAL libraries are only built for the roboRIO, host builds (unit tests) link against
+    // the stubs in `hal_stub` instead.
+    if env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "arm" {
+        link_libs();
+    }
 }
 
 const LIB_DIR: &'static str = "libs";
@@ -0,0 +1,84 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AccelerometerRange {
+    K2G,
+    K4G,
+    K8G,
+    K16G,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Vector3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Vector3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Vector3 {
+        Vector3 { x, y, z }
+    }
+
+    pub fn magnitude(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn dot(self, other: Vector3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Vector3;
+
+    fn add(self, other: Vector3) -> Vector3 {
+        Vector3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Vector3;
+
+    fn sub(self, other: Vector3) -> Vector3 {
+        Vector3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f64> for Vector3 {
+    type Output = Vector3;
+
+    fn mul(self, scale: f64) -> Vector3 {
+        Vector3::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Vector3;
+
+    fn neg(self) -> Vector3 {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+pub trait Accelerometer {
+    /// Sets the measurement range, failing for ranges the sensor doesn't support.
+    fn set_range(&mut self, range: AccelerometerRange) -> HalResult<()>;
+
+    /// Gets the acceleration along the x axis in g.
+    fn get_x(&self) -> HalResult<f64>;
+
+    /// Gets the acceleration along the y axis in g.
+    fn get_y(&self) -> HalResult<f64>;
+
+    /// Gets the acceleration along the z axis in g.
+    fn get_z(&self) -> HalResult<f64>;
+
+    /// Gets the acceleration along all three axes in g, sensors that can read them together
+    /// should override this.
+    fn get_acceleration(&self) -> HalResult<Vector3> {
+        Ok(Vector3::new(self.get_x()?, self.get_y()?, self.get_z()?))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,241 @@
+use std::thread;
+use std::time::Duration;
+
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AccumulatorOutput {
+    pub value: i64,
+    pub count: i64,
+}
+
+impl AccumulatorOutput {
+    pub fn average(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.value as f64 / self.count as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AnalogInput {
+    handle: HAL_AnalogInputHandle,
+    channel: i32,
+    accumulator_offset: i64,
+}
+
+impl AnalogInput {
+    pub fn new(channel: i32) -> HalResult<AnalogInput> {
+        if unsafe { HAL_CheckAnalogInputChannel(channel) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        Ok(AnalogInput {
+            handle: hal_call!(HAL_InitializeAnalogInputPort(HAL_GetPort(channel)))?,
+            channel,
+            accumulator_offset: 0,
+        })
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub(crate) fn get_handle(&self) -> HAL_AnalogInputHandle {
+        self.handle
+    }
+
+    pub fn get_value(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogValue(self.handle))
+    }
+
+    pub fn get_average_value(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogAverageValue(self.handle))
+    }
+
+    pub fn get_voltage(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogVoltage(self.handle))
+    }
+
+    pub fn get_average_voltage(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogAverageVoltage(self.handle))
+    }
+
+    pub fn get_lsb_weight(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogLSBWeight(self.handle))
+    }
+
+    pub fn get_offset(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogOffset(self.handle))
+    }
+
+    /// Averages `2^bits` samples for `get_average_value`, which lowers the effective sample rate.
+    pub fn set_average_bits(&mut self, bits: i32) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogAverageBits(self.handle, bits))
+    }
+
+    pub fn get_average_bits(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogAverageBits(self.handle))
+    }
+
+    /// Accumulates `2^bits` samples for `get_average_value`, which adds resolution to the result.
+    pub fn set_oversample_bits(&mut self, bits: i32) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogOversampleBits(self.handle, bits))
+    }
+
+    pub fn get_oversample_bits(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogOversampleBits(self.handle))
+    }
+
+    /// Sets the samples per second of every analog input.
+    pub fn set_global_sample_rate(samples_per_second: f64) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogSampleRate(samples_per_second))
+    }
+
+    pub fn get_global_sample_rate() -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogSampleRate())
+    }
+
+    pub fn is_accumulator_channel(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_IsAccumulatorChannel(self.handle))? != 0)
+    }
+
+    pub fn init_accumulator(&mut self) -> HalResult<()> {
+        if !self.is_accumulator_channel()? {
+            return Err(HalError(HAL_INVALID_ACCUMULATOR_CHANNEL));
+        }
+
+        self.accumulator_offset = 0;
+        hal_call!(HAL_InitAccumulator(self.handle))
+    }
+
+    /// Sets a value added to every accumulator read, without touching the FPGA accumulator.
+    pub fn set_accumulator_initial_value(&mut self, initial_value: i64) {
+        self.accumulator_offset = initial_value;
+    }
+
+    pub fn reset_accumulator(&mut self) -> HalResult<()> {
+        let sample_time = 1.0 / AnalogInput::get_global_sample_rate()?;
+        let samples = (1 << self.get_oversample_bits()?) * (1 << self.get_average_bits()?);
+        let wait = sample_time * f64::from(samples);
+
+        // A zero or negative sample rate would never produce the next sample.
+        if !wait.is_finite() || wait < 0.0 {
+            return Err(HalError(INCOMPATIBLE_STATE));
+        }
+
+        hal_call!(HAL_ResetAccumulator(self.handle))?;
+
+        // wait for the next sample so the next read does not return the old value
+        thread::sleep(Duration::from_secs_f64(wait));
+
+        Ok(())
+    }
+
+    /// Sets the raw value subtracted from every sample before it is accumulated.
+    pub fn set_accumulator_center(&mut self, center: i32) -> HalResult<()> {
+        hal_call!(HAL_SetAccumulatorCenter(self.handle, center))
+    }
+
+    pub fn set_accumulator_deadband(&mut self, deadband: i32) -> HalResult<()> {
+        hal_call!(HAL_SetAccumulatorDeadband(self.handle, deadband))
+    }
+
+    pub fn get_accumulator_value(&self) -> HalResult<i64> {
+        Ok(hal_call!(HAL_GetAccumulatorValue(self.handle))? + self.accumulator_offset)
+    }
+
+    pub fn get_accumulator_count(&self) -> HalResult<i64> {
+        hal_call!(HAL_GetAccumulatorCount(self.handle))
+    }
+
+    /// Reads the value and count together so they come from the same sample.
+    pub fn get_accumulator_output(&self) -> HalResult<AccumulatorOutput> {
+        let mut output = AccumulatorOutput::default();
+
+        hal_call!(HAL_GetAccumulatorOutput(self.handle, &mut output.value, &mut output.count))?;
+        output.value += self.accumulator_offset;
+
+        Ok(output)
+    }
+}
+
+impl Drop for AnalogInput {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_FreeAnalogInputPort(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        assert_eq!(AnalogInput::new(-1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(AnalogInput::new(hal_stub::NUM_ANALOG_INPUTS).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn drop_frees_port() {
+        let input = AnalogInput::new(2).unwrap();
+        assert_eq!(AnalogInput::new(2).unwrap_err().0, RESOURCE_IS_ALLOCATED);
+
+        let handle = input.handle;
+        drop(input);
+        assert!(hal_stub::analog_input(handle).freed);
+        assert!(AnalogInput::new(2).is_ok());
+    }
+
+    #[test]
+    fn accumulator_needs_an_accumulator_channel() {
+        let mut input = AnalogInput::new(2).unwrap();
+        hal_stub::set_analog_voltage(input.handle, 2.5);
+        assert_eq!(input.get_voltage().unwrap(), 2.5);
+
+        assert!(!input.is_accumulator_channel().unwrap());
+        assert_eq!(input.init_accumulator().unwrap_err().0, HAL_INVALID_ACCUMULATOR_CHANNEL);
+    }
+
+    #[test]
+    fn accumulator_offset_is_added_to_reads() {
+        let mut input = AnalogInput::new(0).unwrap();
+        input.init_accumulator().unwrap();
+        hal_stub::set_accumulator(input.handle, 300, 4);
+
+        input.set_accumulator_initial_value(100);
+        assert_eq!(input.get_accumulator_value().unwrap(), 400);
+        assert_eq!(input.get_accumulator_count().unwrap(), 4);
+        assert_eq!(input.get_accumulator_output().unwrap(), AccumulatorOutput { value: 400, count: 4 });
+        assert_eq!(input.get_accumulator_output().unwrap().average(), 100.0);
+
+        // Re-initializing drops the offset along with the FPGA's sum.
+        input.init_accumulator().unwrap();
+        assert_eq!(input.get_accumulator_value().unwrap(), 0);
+        assert_eq!(AccumulatorOutput::default().average(), 0.0);
+    }
+
+    #[test]
+    fn reset_accumulator_rejects_a_stopped_sample_clock() {
+        let mut input = AnalogInput::new(1).unwrap();
+        input.init_accumulator().unwrap();
+        hal_stub::set_accumulator(input.handle, 300, 4);
+
+        for rate in [0.0, -1.0].iter() {
+            AnalogInput::set_global_sample_rate(*rate).unwrap();
+            assert_eq!(input.reset_accumulator().unwrap_err().0, INCOMPATIBLE_STATE);
+            assert_eq!(input.get_accumulator_count().unwrap(), 4);
+        }
+
+        AnalogInput::set_global_sample_rate(1_000_000.0).unwrap();
+        input.set_average_bits(2).unwrap();
+        input.set_oversample_bits(3).unwrap();
+        input.reset_accumulator().unwrap();
+        assert_eq!(input.get_accumulator_count().unwrap(), 0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,191 @@
+use rbothal::*;
+
+use crate::analog::AnalogInput;
+use crate::gyro::Gyro;
+
+// matches the sensitivity `HAL_SetupAnalogGyro` configures for the KOP gyro
+const DEFAULT_VOLTS_PER_DEGREE_PER_SECOND: f64 = 0.007;
+
+/// The values found by a calibration, save these to skip calibrating on the next boot.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnalogGyroCalibration {
+    pub volts_per_degree_per_second: f64,
+    pub offset: f64,
+    pub center: i32,
+}
+
+#[derive(Debug)]
+pub struct AnalogGyro {
+    handle: HAL_GyroHandle,
+    input: AnalogInput,
+    volts_per_degree_per_second: f64,
+}
+
+impl AnalogGyro {
+    /// Creates a gyro on an accumulator channel and calibrates it, which blocks for 5 seconds.
+    pub fn new(channel: i32) -> HalResult<AnalogGyro> {
+        let mut gyro = AnalogGyro::from_input(AnalogInput::new(channel)?)?;
+
+        gyro.calibrate()?;
+        Ok(gyro)
+    }
+
+    /// Creates a gyro from a previous calibration instead of calibrating again.
+    pub fn with_calibration(channel: i32, calibration: AnalogGyroCalibration) -> HalResult<AnalogGyro> {
+        let mut gyro = AnalogGyro::from_input(AnalogInput::new(channel)?)?;
+
+        gyro.set_calibration(calibration)?;
+        gyro.reset()?;
+        Ok(gyro)
+    }
+
+    fn from_input(input: AnalogInput) -> HalResult<AnalogGyro> {
+        if !input.is_accumulator_channel()? {
+            return Err(HalError(HAL_INVALID_ACCUMULATOR_CHANNEL));
+        }
+
+        let handle = hal_call!(HAL_InitializeAnalogGyro(input.get_handle()))?;
+        let gyro = AnalogGyro {
+            handle,
+            input,
+            volts_per_degree_per_second: DEFAULT_VOLTS_PER_DEGREE_PER_SECOND,
+        };
+
+        hal_call!(HAL_SetupAnalogGyro(handle))?;
+        Ok(gyro)
+    }
+
+    pub fn get_input(&self) -> &AnalogInput {
+        &self.input
+    }
+
+    pub fn set_sensitivity(&mut self, volts_per_degree_per_second: f64) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogGyroVoltsPerDegreePerSecond(self.handle, volts_per_degree_per_second))?;
+
+        self.volts_per_degree_per_second = volts_per_degree_per_second;
+        Ok(())
+    }
+
+    /// Sets the voltage around the center that is treated as no rotation.
+    pub fn set_deadband(&mut self, volts: f64) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogGyroDeadband(self.handle, volts))
+    }
+
+    pub fn get_offset(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogGyroOffset(self.handle))
+    }
+
+    pub fn get_center(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogGyroCenter(self.handle))
+    }
+
+    pub fn get_calibration(&self) -> HalResult<AnalogGyroCalibration> {
+        Ok(AnalogGyroCalibration {
+            volts_per_degree_per_second: self.volts_per_degree_per_second,
+            offset: self.get_offset()?,
+            center: self.get_center()?,
+        })
+    }
+
+    pub fn set_calibration(&mut self, calibration: AnalogGyroCalibration) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogGyroParameters(
+            self.handle,
+            calibration.volts_per_degree_per_second,
+            calibration.offset,
+            calibration.center,
+        ))?;
+
+        self.volts_per_degree_per_second = calibration.volts_per_degree_per_second;
+        Ok(())
+    }
+}
+
+impl Gyro for AnalogGyro {
+    fn calibrate(&mut self) -> HalResult<()> {
+        hal_call!(HAL_CalibrateAnalogGyro(self.handle))
+    }
+
+    fn reset(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ResetAnalogGyro(self.handle))
+    }
+
+    fn get_angle(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogGyroAngle(self.handle))
+    }
+
+    fn get_rate(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogGyroRate(self.handle))
+    }
+}
+
+impl Drop for AnalogGyro {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_FreeAnalogGyro(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    const CALIBRATION: AnalogGyroCalibration = AnalogGyroCalibration {
+        volts_per_degree_per_second: 0.0125,
+        offset: 0.02,
+        center: 2050,
+    };
+
+    #[test]
+    fn non_accumulator_channel_is_rejected() {
+        let error = AnalogGyro::with_calibration(2, CALIBRATION).unwrap_err();
+
+        assert_eq!(error.0, HAL_INVALID_ACCUMULATOR_CHANNEL);
+        assert!(AnalogInput::new(2).is_ok());
+    }
+
+    #[test]
+    fn new_calibrates() {
+        let gyro = AnalogGyro::new(0).unwrap();
+        let state = hal_stub::analog_gyro(gyro.handle);
+
+        assert_eq!(state.input, gyro.get_input().get_handle());
+        assert_eq!(state.volts_per_degree_per_second, DEFAULT_VOLTS_PER_DEGREE_PER_SECOND);
+        assert_eq!(state.calibrations, 1);
+    }
+
+    #[test]
+    fn saved_calibration_round_trips() {
+        let mut gyro = AnalogGyro::with_calibration(1, CALIBRATION).unwrap();
+        let state = hal_stub::analog_gyro(gyro.handle);
+        assert_eq!((state.calibrations, state.resets), (0, 1));
+        assert_eq!(gyro.get_calibration().unwrap(), CALIBRATION);
+
+        gyro.set_sensitivity(0.005).unwrap();
+        assert_eq!(gyro.get_calibration().unwrap().volts_per_degree_per_second, 0.005);
+        assert_eq!(hal_stub::analog_gyro(gyro.handle).volts_per_degree_per_second, 0.005);
+    }
+
+    #[test]
+    fn reset_zeroes_the_heading() {
+        let mut gyro = AnalogGyro::with_calibration(0, CALIBRATION).unwrap();
+        hal_stub::set_analog_gyro_angle(gyro.handle, 450.0);
+
+        let heading: &mut dyn Gyro = &mut gyro;
+        assert_eq!(heading.get_angle().unwrap(), 450.0);
+        heading.reset().unwrap();
+        assert_eq!(heading.get_angle().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn drop_frees_gyro_and_input() {
+        let gyro = AnalogGyro::with_calibration(0, CALIBRATION).unwrap();
+        let handle = gyro.handle;
+        let input = gyro.get_input().get_handle();
+
+        drop(gyro);
+        assert!(hal_stub::analog_gyro(handle).freed);
+        assert!(hal_stub::analog_input(input).freed);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,80 @@
+use rbothal::*;
+
+#[derive(Debug)]
+pub struct AnalogOutput {
+    handle: HAL_AnalogOutputHandle,
+    channel: i32,
+}
+
+impl AnalogOutput {
+    pub fn new(channel: i32) -> HalResult<AnalogOutput> {
+        if unsafe { HAL_CheckAnalogOutputChannel(channel) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        Ok(AnalogOutput {
+            handle: hal_call!(HAL_InitializeAnalogOutputPort(HAL_GetPort(channel)))?,
+            channel,
+        })
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    /// Sets the output voltage, from 0 to 5 volts.
+    pub fn set_voltage(&mut self, voltage: f64) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogOutput(self.handle, voltage))
+    }
+
+    pub fn get_voltage(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetAnalogOutput(self.handle))
+    }
+}
+
+impl Drop for AnalogOutput {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_FreeAnalogOutputPort(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn channel_is_validated() {
+        assert_eq!(AnalogOutput::new(-1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(AnalogOutput::new(hal_stub::NUM_ANALOG_OUTPUTS).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+
+        let output = AnalogOutput::new(1).unwrap();
+        assert_eq!(output.get_channel(), 1);
+        assert_eq!(AnalogOutput::new(1).unwrap_err().0, RESOURCE_IS_ALLOCATED);
+    }
+
+    #[test]
+    fn sets_voltage() {
+        let mut output = AnalogOutput::new(0).unwrap();
+
+        output.set_voltage(2.5).unwrap();
+        assert_eq!(output.get_voltage().unwrap(), 2.5);
+
+        output.set_voltage(6.0).unwrap();
+        assert_eq!(output.get_voltage().unwrap(), 5.0);
+    }
+
+    #[test]
+    fn drop_frees_the_port() {
+        let output = AnalogOutput::new(0).unwrap();
+        let handle = output.handle;
+
+        assert_eq!(hal_stub::analog_output(handle).channel, 0);
+
+        drop(output);
+        assert!(hal_stub::analog_output(handle).freed);
+        assert!(AnalogOutput::new(0).is_ok());
+    }
+}
\ No newline at end of file
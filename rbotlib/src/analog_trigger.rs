@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use rbothal::*;
+
+use crate::analog::AnalogInput;
+use crate::dio::DigitalSource;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AnalogTriggerType {
+    /// High while the input is between the lower and upper limits.
+    InWindow,
+    /// High once the input rises above the upper limit, until it falls below the lower limit.
+    State,
+    /// Pulses when the state output rises, only usable as a source for counters.
+    RisingPulse,
+    /// Pulses when the state output falls, only usable as a source for counters.
+    FallingPulse,
+}
+
+impl From<AnalogTriggerType> for HAL_AnalogTriggerType::Type {
+    fn from(trigger_type: AnalogTriggerType) -> HAL_AnalogTriggerType::Type {
+        match trigger_type {
+            AnalogTriggerType::InWindow => HAL_AnalogTriggerType::HAL_Trigger_kInWindow,
+            AnalogTriggerType::State => HAL_AnalogTriggerType::HAL_Trigger_kState,
+            AnalogTriggerType::RisingPulse => HAL_AnalogTriggerType::HAL_Trigger_kRisingPulse,
+            AnalogTriggerType::FallingPulse => HAL_AnalogTriggerType::HAL_Trigger_kFallingPulse,
+        }
+    }
+}
+
+// Shared with every output so the trigger outlives the counters and encoders routed from it.
+#[derive(Debug)]
+struct TriggerHandle {
+    handle: HAL_AnalogTriggerHandle,
+    _input: AnalogInput,
+}
+
+impl Drop for TriggerHandle {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_CleanAnalogTrigger(self.handle));
+    }
+}
+
+#[derive(Debug)]
+pub struct AnalogTrigger {
+    trigger: Arc<TriggerHandle>,
+}
+
+impl AnalogTrigger {
+    pub fn new(channel: i32) -> HalResult<AnalogTrigger> {
+        AnalogTrigger::from_input(AnalogInput::new(channel)?)
+    }
+
+    pub fn from_input(input: AnalogInput) -> HalResult<AnalogTrigger> {
+        let handle = hal_call!(HAL_InitializeAnalogTrigger(input.get_handle()))?;
+
+        Ok(AnalogTrigger {
+            trigger: Arc::new(TriggerHandle { handle, _input: input }),
+        })
+    }
+
+    pub fn get_index(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetAnalogTriggerFPGAIndex(self.trigger.handle))
+    }
+
+    pub fn set_limits_raw(&mut self, lower: i32, upper: i32) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogTriggerLimitsRaw(self.trigger.handle, lower, upper))
+    }
+
+    pub fn set_limits_voltage(&mut self, lower: f64, upper: f64) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogTriggerLimitsVoltage(self.trigger.handle, lower, upper))
+    }
+
+    /// Compares the averaged value against the limits, cannot be combined with `set_filtered`.
+    pub fn set_averaged(&mut self, use_averaged_value: bool) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogTriggerAveraged(self.trigger.handle, use_averaged_value as HAL_Bool))
+    }
+
+    /// Compares a 3 point rejection filtered value against the limits, useful for 360 degree
+    /// potentiometers wrapping through zero. Cannot be combined with `set_averaged`.
+    pub fn set_filtered(&mut self, use_filtered_value: bool) -> HalResult<()> {
+        hal_call!(HAL_SetAnalogTriggerFiltered(self.trigger.handle, use_filtered_value as HAL_Bool))
+    }
+
+    pub fn in_window(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetAnalogTriggerInWindow(self.trigger.handle))? != 0)
+    }
+
+    pub fn trigger_state(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetAnalogTriggerTriggerState(self.trigger.handle))? != 0)
+    }
+
+    pub fn create_output(&self, output_type: AnalogTriggerType) -> AnalogTriggerOutput {
+        AnalogTriggerOutput {
+            trigger: Arc::clone(&self.trigger),
+            output_type,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AnalogTriggerOutput {
+    trigger: Arc<TriggerHandle>,
+    output_type: AnalogTriggerType,
+}
+
+impl AnalogTriggerOutput {
+    pub fn get_type(&self) -> AnalogTriggerType {
+        self.output_type
+    }
+
+    pub fn get(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetAnalogTriggerOutput(self.trigger.handle, self.output_type.into()))? != 0)
+    }
+}
+
+impl DigitalSource for AnalogTriggerOutput {
+    fn get_port_handle_for_routing(&self) -> HAL_Handle {
+        self.trigger.handle
+    }
+
+    fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type {
+        self.output_type.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    fn handle(trigger: &AnalogTrigger) -> HAL_AnalogTriggerHandle {
+        trigger.trigger.handle
+    }
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        assert_eq!(AnalogTrigger::new(hal_stub::NUM_ANALOG_INPUTS).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn limits_must_be_ordered() {
+        let mut trigger = AnalogTrigger::new(0).unwrap();
+
+        assert_eq!(trigger.set_limits_voltage(3.0, 1.0).unwrap_err().0, ANALOG_TRIGGER_LIMIT_ORDER_ERROR);
+        trigger.set_limits_voltage(1.0, 3.0).unwrap();
+        let state = hal_stub::analog_trigger(handle(&trigger));
+        assert_eq!((state.lower, state.upper), (1.0, 3.0));
+    }
+
+    #[test]
+    fn averaged_and_filtered_are_exclusive() {
+        let mut trigger = AnalogTrigger::new(1).unwrap();
+
+        trigger.set_averaged(true).unwrap();
+        assert_eq!(trigger.set_filtered(true).unwrap_err().0, INCOMPATIBLE_STATE);
+
+        trigger.set_averaged(false).unwrap();
+        trigger.set_filtered(true).unwrap();
+        assert_eq!(trigger.set_averaged(true).unwrap_err().0, INCOMPATIBLE_STATE);
+        assert!(hal_stub::analog_trigger(handle(&trigger)).filtered);
+    }
+
+    #[test]
+    fn state_latches_between_limits() {
+        let mut trigger = AnalogTrigger::new(2).unwrap();
+        let input = hal_stub::analog_trigger(handle(&trigger)).input;
+        trigger.set_limits_voltage(1.0, 3.0).unwrap();
+
+        let in_window = trigger.create_output(AnalogTriggerType::InWindow);
+        let state = trigger.create_output(AnalogTriggerType::State);
+
+        // (voltage, in window, state)
+        let cases = [
+            (0.5, false, false),
+            (2.0, true, false),
+            (3.5, false, true),
+            (2.0, true, true),
+            (0.5, false, false),
+        ];
+
+        for (voltage, expected_in_window, expected_state) in cases.iter() {
+            hal_stub::set_analog_voltage(input, *voltage);
+
+            assert_eq!(trigger.in_window().unwrap(), *expected_in_window);
+            assert_eq!(in_window.get().unwrap(), *expected_in_window);
+            assert_eq!(trigger.trigger_state().unwrap(), *expected_state);
+            assert_eq!(state.get().unwrap(), *expected_state);
+        }
+    }
+
+    #[test]
+    fn pulse_outputs_only_route() {
+        let trigger = AnalogTrigger::new(3).unwrap();
+        let rising = trigger.create_output(AnalogTriggerType::RisingPulse);
+
+        assert_eq!(rising.get().unwrap_err().0, ANALOG_TRIGGER_PULSE_OUTPUT_ERROR);
+        assert_eq!(rising.get_port_handle_for_routing(), handle(&trigger));
+        assert_eq!(rising.get_analog_trigger_type_for_routing(), HAL_AnalogTriggerType::HAL_Trigger_kRisingPulse);
+    }
+
+    #[test]
+    fn outputs_keep_the_trigger_alive() {
+        let trigger = AnalogTrigger::new(4).unwrap();
+        let output = trigger.create_output(AnalogTriggerType::State);
+        let trigger_handle = handle(&trigger);
+        let input = hal_stub::analog_trigger(trigger_handle).input;
+
+        drop(trigger);
+        assert!(!hal_stub::analog_trigger(trigger_handle).cleaned);
+        assert!(output.get().is_ok());
+
+        drop(output);
+        assert!(hal_stub::analog_trigger(trigger_handle).cleaned);
+        assert!(hal_stub::analog_input(input).freed);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use rbothal::*;
+
+use crate::accelerometer::{Accelerometer, AccelerometerRange};
+
+/// The accelerometer inside the roboRIO.
+#[derive(Debug)]
+pub struct BuiltInAccelerometer {
+    range: AccelerometerRange,
+}
+
+impl BuiltInAccelerometer {
+    pub fn new(range: AccelerometerRange) -> HalResult<BuiltInAccelerometer> {
+        let mut accelerometer = BuiltInAccelerometer { range };
+
+        accelerometer.set_range(range)?;
+        Ok(accelerometer)
+    }
+
+    pub fn get_range(&self) -> AccelerometerRange {
+        self.range
+    }
+}
+
+impl Accelerometer for BuiltInAccelerometer {
+    /// Supports up to 8G.
+    fn set_range(&mut self, range: AccelerometerRange) -> HalResult<()> {
+        let hal_range = match range {
+            AccelerometerRange::K2G => HAL_AccelerometerRange::k2G,
+            AccelerometerRange::K4G => HAL_AccelerometerRange::k4G,
+            AccelerometerRange::K8G => HAL_AccelerometerRange::k8G,
+            AccelerometerRange::K16G => return Err(HalError(PARAMETER_OUT_OF_RANGE)),
+        };
+
+        // The range can only be changed while the accelerometer is inactive.
+        unsafe {
+            HAL_SetAccelerometerActive(0);
+            HAL_SetAccelerometerRange(hal_range);
+            HAL_SetAccelerometerActive(1);
+        }
+
+        self.range = range;
+        Ok(())
+    }
+
+    fn get_x(&self) -> HalResult<f64> {
+        Ok(unsafe { HAL_GetAccelerometerX() })
+    }
+
+    fn get_y(&self) -> HalResult<f64> {
+        Ok(unsafe { HAL_GetAccelerometerY() })
+    }
+
+    fn get_z(&self) -> HalResult<f64> {
+        Ok(unsafe { HAL_GetAccelerometerZ() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accelerometer::Vector3;
+    use crate::hal_stub;
+
+    #[test]
+    fn range_is_set_while_inactive() {
+        let mut accelerometer = BuiltInAccelerometer::new(AccelerometerRange::K2G).unwrap();
+        assert_eq!(hal_stub::accelerometer().range, HAL_AccelerometerRange::k2G);
+
+        accelerometer.set_range(AccelerometerRange::K8G).unwrap();
+        assert_eq!(accelerometer.get_range(), AccelerometerRange::K8G);
+
+        let state = hal_stub::accelerometer();
+        assert_eq!(state.range, HAL_AccelerometerRange::k8G);
+        assert!(state.active);
+        assert!(!state.range_set_while_active);
+    }
+
+    #[test]
+    fn unsupported_range_is_rejected() {
+        let error = BuiltInAccelerometer::new(AccelerometerRange::K16G).unwrap_err();
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+
+        let mut accelerometer = BuiltInAccelerometer::new(AccelerometerRange::K4G).unwrap();
+        let error = accelerometer.set_range(AccelerometerRange::K16G).unwrap_err();
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(accelerometer.get_range(), AccelerometerRange::K4G);
+        assert_eq!(hal_stub::accelerometer().range, HAL_AccelerometerRange::k4G);
+    }
+
+    #[test]
+    fn reads_each_axis() {
+        let accelerometer = BuiltInAccelerometer::new(AccelerometerRange::K2G).unwrap();
+        hal_stub::set_acceleration(0.25, -0.5, 1.0);
+
+        assert_eq!(accelerometer.get_x().unwrap(), 0.25);
+        assert_eq!(accelerometer.get_y().unwrap(), -0.5);
+        assert_eq!(accelerometer.get_z().unwrap(), 1.0);
+        assert_eq!(accelerometer.get_acceleration().unwrap(), Vector3::new(0.25, -0.5, 1.0));
+    }
+}
\ No newline at end of file
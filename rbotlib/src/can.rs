@@ -0,0 +1,460 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CanManufacturer {
+    Broadcast = 0,
+    Ni = 1,
+    Lm = 2,
+    Deka = 3,
+    Ctre = 4,
+    Rev = 5,
+    Grapple = 6,
+    Ms = 7,
+    /// For team built devices.
+    TeamUse = 8,
+    KauaiLabs = 9,
+    Copperforge = 10,
+    Pwf = 11,
+    Studica = 12,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CanDeviceType {
+    Broadcast = 0,
+    RobotController = 1,
+    MotorController = 2,
+    RelayController = 3,
+    GyroSensor = 4,
+    Accelerometer = 5,
+    UltrasonicSensor = 6,
+    GearToothSensor = 7,
+    PowerDistribution = 8,
+    Pneumatics = 9,
+    /// For team built devices.
+    Miscellaneous = 10,
+    FirmwareUpdate = 31,
+}
+
+/// Builds the 29 bit arbitration id of the FRC CAN layout, where the api id is the 6 bit class
+/// followed by the 4 bit index.
+pub fn arbitration_id(
+    device_type: CanDeviceType,
+    manufacturer: CanManufacturer,
+    api_id: i32,
+    device_number: i32,
+) -> u32 {
+    ((device_type as u32 & 0x1f) << 24)
+        | ((manufacturer as u32 & 0xff) << 16)
+        | ((api_id as u32 & 0x3ff) << 6)
+        | (device_number as u32 & 0x3f)
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CanFrame {
+    pub data: [u8; 8],
+    pub length: usize,
+    /// When the frame was received in milliseconds, on the roboRIO's monotonic clock.
+    pub timestamp: u64,
+}
+
+impl CanFrame {
+    /// Gets the bytes actually received.
+    pub fn get_data(&self) -> &[u8] {
+        &self.data[..self.length.min(8)]
+    }
+}
+
+/// A device on the CAN bus using the FRC CAN layout, addressed by manufacturer, type and number.
+#[derive(Debug)]
+pub struct CanDevice {
+    handle: HAL_CANHandle,
+    manufacturer: CanManufacturer,
+    device_type: CanDeviceType,
+    device_number: i32,
+}
+
+impl CanDevice {
+    pub fn new(manufacturer: CanManufacturer, device_number: i32, device_type: CanDeviceType) -> HalResult<CanDevice> {
+        if !(0..64).contains(&device_number) {
+            return Err(HalError(PARAMETER_OUT_OF_RANGE));
+        }
+
+        Ok(CanDevice {
+            handle: hal_call!(HAL_InitializeCAN(
+                manufacturer as HAL_CANManufacturer::Type,
+                device_number,
+                device_type as HAL_CANDeviceType::Type,
+            ))?,
+            manufacturer,
+            device_type,
+            device_number,
+        })
+    }
+
+    /// Creates a device with the manufacturer and type reserved for team use.
+    pub fn team_use(device_number: i32) -> HalResult<CanDevice> {
+        CanDevice::new(CanManufacturer::TeamUse, device_number, CanDeviceType::Miscellaneous)
+    }
+
+    pub fn get_manufacturer(&self) -> CanManufacturer {
+        self.manufacturer
+    }
+
+    pub fn get_device_type(&self) -> CanDeviceType {
+        self.device_type
+    }
+
+    pub fn get_device_number(&self) -> i32 {
+        self.device_number
+    }
+
+    /// Gets the full arbitration id of frames sent with `api_id`.
+    pub fn get_arbitration_id(&self, api_id: i32) -> u32 {
+        arbitration_id(self.device_type, self.manufacturer, api_id, self.device_number)
+    }
+
+    fn check_length(data: &[u8]) -> HalResult<()> {
+        if data.len() > 8 {
+            Err(HalError(PARAMETER_OUT_OF_RANGE))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn write_packet(&mut self, api_id: i32, data: &[u8]) -> HalResult<()> {
+        CanDevice::check_length(data)?;
+        hal_call!(HAL_WriteCANPacket(self.handle, data.as_ptr(), data.len() as i32, api_id))
+    }
+
+    /// Sends the packet now and keeps resending it every `period` until stopped or replaced.
+    pub fn write_packet_repeating(&mut self, api_id: i32, data: &[u8], period: Duration) -> HalResult<()> {
+        CanDevice::check_length(data)?;
+        hal_call!(HAL_WriteCANPacketRepeating(
+            self.handle,
+            data.as_ptr(),
+            data.len() as i32,
+            api_id,
+            period.as_millis() as i32,
+        ))
+    }
+
+    /// Requests a frame of `length` bytes, which must match what the device sends back.
+    pub fn write_rtr_frame(&mut self, api_id: i32, length: usize) -> HalResult<()> {
+        if length > 8 {
+            return Err(HalError(PARAMETER_OUT_OF_RANGE));
+        }
+
+        hal_call!(HAL_WriteCANRTRFrame(self.handle, length as i32, api_id))
+    }
+
+    pub fn stop_repeating(&mut self, api_id: i32) -> HalResult<()> {
+        hal_call!(HAL_StopCANPacketRepeating(self.handle, api_id))
+    }
+
+    // Missing or stale frames are reported through these codes rather than being errors.
+    fn read_with<F>(read: F) -> HalResult<Option<CanFrame>>
+    where
+        F: FnOnce(*mut u8, *mut i32, *mut u64, *mut i32),
+    {
+        let mut frame = CanFrame::default();
+        let mut length = 0;
+        let mut status = 0;
+
+        read(frame.data.as_mut_ptr(), &mut length, &mut frame.timestamp, &mut status);
+
+        match status {
+            0 => {
+                frame.length = length as usize;
+                Ok(Some(frame))
+            }
+            _ if status == HAL_ERR_CANSessionMux_MessageNotFound || status == HAL_CAN_TIMEOUT => Ok(None),
+            _ => Err(HalError(status)),
+        }
+    }
+
+    /// Reads a frame only if one arrived since the last read.
+    pub fn read_new(&mut self, api_id: i32) -> HalResult<Option<CanFrame>> {
+        let handle = self.handle;
+
+        CanDevice::read_with(|data, length, timestamp, status| unsafe {
+            HAL_ReadCANPacketNew(handle, api_id, data, length, timestamp, status)
+        })
+    }
+
+    /// Reads the last frame received, however old.
+    pub fn read_latest(&mut self, api_id: i32) -> HalResult<Option<CanFrame>> {
+        let handle = self.handle;
+
+        CanDevice::read_with(|data, length, timestamp, status| unsafe {
+            HAL_ReadCANPacketLatest(handle, api_id, data, length, timestamp, status)
+        })
+    }
+
+    /// Reads the last frame received, unless it is older than `timeout`.
+    pub fn read_timeout(&mut self, api_id: i32, timeout: Duration) -> HalResult<Option<CanFrame>> {
+        let handle = self.handle;
+
+        CanDevice::read_with(|data, length, timestamp, status| unsafe {
+            HAL_ReadCANPacketTimeout(handle, api_id, data, length, timestamp, timeout.as_millis() as i32, status)
+        })
+    }
+}
+
+impl Drop for CanDevice {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_CleanCAN(self.handle);
+        }
+    }
+}
+
+/// A frame seen on the bus by a `CanStream`, with any arbitration id.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RawCanFrame {
+    /// The 29 bit arbitration id, or 11 bit for standard frames.
+    pub id: u32,
+    pub is_remote: bool,
+    pub is_11bit: bool,
+    pub data: [u8; 8],
+    pub length: usize,
+    /// When the frame was received in milliseconds, on the roboRIO's monotonic clock.
+    pub timestamp: u32,
+}
+
+impl RawCanFrame {
+    pub fn get_data(&self) -> &[u8] {
+        &self.data[..self.length.min(8)]
+    }
+}
+
+impl From<HAL_CANStreamMessage> for RawCanFrame {
+    fn from(message: HAL_CANStreamMessage) -> RawCanFrame {
+        RawCanFrame {
+            id: message.messageID & 0x1fff_ffff,
+            is_remote: message.messageID & HAL_CAN_IS_FRAME_REMOTE != 0,
+            is_11bit: message.messageID & HAL_CAN_IS_FRAME_11BIT != 0,
+            data: message.data,
+            length: usize::from(message.dataSize),
+            timestamp: message.timeStamp,
+        }
+    }
+}
+
+// Frames read from the session per call, the stream buffers what it hasn't handed out yet.
+const STREAM_BATCH_SIZE: usize = 64;
+
+/// Captures every frame whose id matches `id` in the bits set in `mask`.
+///
+/// Iterating yields the frames captured so far and ends once none are waiting, so it can be
+/// iterated again every loop. A read error is yielded once, after which iteration always ends.
+#[derive(Debug)]
+pub struct CanStream {
+    session: u32,
+    buffer: VecDeque<RawCanFrame>,
+    overruns: u32,
+    error: Option<HalError>,
+}
+
+impl CanStream {
+    /// Opens a session buffering up to `max_messages` frames between reads.
+    pub fn new(id: u32, mask: u32, max_messages: u32) -> HalResult<CanStream> {
+        let mut session = 0;
+
+        hal_call!(HAL_CAN_OpenStreamSession(&mut session, id, mask, max_messages))?;
+        Ok(CanStream {
+            session,
+            buffer: VecDeque::new(),
+            overruns: 0,
+            error: None,
+        })
+    }
+
+    /// Opens a session capturing all traffic on the bus.
+    pub fn all(max_messages: u32) -> HalResult<CanStream> {
+        CanStream::new(0, 0, max_messages)
+    }
+
+    /// Gets the number of reads that found the session had dropped frames.
+    pub fn get_overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Gets the read error that ended the stream, if any.
+    pub fn get_error(&self) -> Option<HalError> {
+        self.error
+    }
+
+    fn fill(&mut self) -> HalResult<()> {
+        let mut messages = [HAL_CANStreamMessage::default(); STREAM_BATCH_SIZE];
+        let mut read = 0;
+        let mut status = 0;
+
+        unsafe {
+            HAL_CAN_ReadStreamSession(
+                self.session,
+                messages.as_mut_ptr(),
+                STREAM_BATCH_SIZE as u32,
+                &mut read,
+                &mut status,
+            );
+        }
+
+        match status {
+            0 => {}
+            _ if status == HAL_ERR_CANSessionMux_SessionOverrun as i32 => self.overruns += 1,
+            _ if status == HAL_ERR_CANSessionMux_MessageNotFound => {}
+            _ => return Err(HalError(status)),
+        }
+
+        self.buffer
+            .extend(messages[..read as usize].iter().map(|&message| RawCanFrame::from(message)));
+        Ok(())
+    }
+}
+
+impl Iterator for CanStream {
+    type Item = HalResult<RawCanFrame>;
+
+    fn next(&mut self) -> Option<HalResult<RawCanFrame>> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        if self.buffer.is_empty() {
+            if let Err(error) = self.fill() {
+                self.error = Some(error);
+                return Some(Err(error));
+            }
+        }
+
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+impl Drop for CanStream {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_CAN_CloseStreamSession(self.session);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CanBusStatus {
+    pub percent_bus_utilization: f32,
+    pub bus_off_count: u32,
+    pub tx_full_count: u32,
+    pub receive_error_count: u32,
+    pub transmit_error_count: u32,
+}
+
+pub fn get_bus_status() -> HalResult<CanBusStatus> {
+    let mut status = CanBusStatus::default();
+
+    hal_call!(HAL_CAN_GetCANStatus(
+        &mut status.percent_bus_utilization,
+        &mut status.bus_off_count,
+        &mut status.tx_full_count,
+        &mut status.receive_error_count,
+        &mut status.transmit_error_count,
+    ))?;
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn device_number_is_validated() {
+        assert_eq!(CanDevice::team_use(-1).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(CanDevice::team_use(64).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+
+        let device = CanDevice::new(CanManufacturer::Rev, 63, CanDeviceType::MotorController).unwrap();
+        let state = hal_stub::can_device(device.handle);
+        assert_eq!(state.manufacturer, HAL_CANManufacturer::HAL_CAN_Man_kREV);
+        assert_eq!(state.device_type, HAL_CANDeviceType::HAL_CAN_Dev_kMotorController);
+        assert_eq!(state.device_number, 63);
+        assert_eq!(device.get_arbitration_id(0x2a), 0x0205_0abf);
+    }
+
+    #[test]
+    fn writes_are_limited_to_eight_bytes() {
+        let mut device = CanDevice::team_use(5).unwrap();
+
+        assert_eq!(device.write_packet(1, &[0; 9]).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        let error = device.write_packet_repeating(2, &[0; 9], Duration::from_millis(20)).unwrap_err();
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(device.write_rtr_frame(3, 9).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+
+        let state = hal_stub::can_device(device.handle);
+        assert!(state.sent.is_empty() && state.repeating.is_empty() && state.rtr_frames.is_empty());
+
+        device.write_packet(1, &[1, 2]).unwrap();
+        device.write_packet_repeating(2, &[3], Duration::from_millis(20)).unwrap();
+        device.write_rtr_frame(3, 8).unwrap();
+
+        let state = hal_stub::can_device(device.handle);
+        assert_eq!(state.sent, vec![(1, vec![1, 2]), (2, vec![3])]);
+        assert_eq!(state.repeating[&2], (vec![3], 20));
+        assert_eq!(state.rtr_frames, vec![(3, 8)]);
+
+        device.stop_repeating(2).unwrap();
+        assert!(hal_stub::can_device(device.handle).repeating.is_empty());
+    }
+
+    #[test]
+    fn missing_and_stale_frames_read_as_none() {
+        let mut device = CanDevice::team_use(7).unwrap();
+
+        assert_eq!(device.read_new(4).unwrap(), None);
+        assert_eq!(device.read_latest(4).unwrap(), None);
+        assert_eq!(device.read_timeout(4, Duration::from_millis(50)).unwrap(), None);
+
+        hal_stub::receive_can_frame(device.handle, 4, &[0xde, 0xad]);
+        let frame = device.read_new(4).unwrap().unwrap();
+        assert_eq!(frame.get_data(), &[0xde, 0xad]);
+        assert_eq!(device.read_new(4).unwrap(), None);
+        assert_eq!(device.read_latest(4).unwrap(), Some(frame));
+
+        hal_stub::advance_can_time(100);
+        assert_eq!(device.read_latest(4).unwrap(), Some(frame));
+        assert_eq!(device.read_timeout(4, Duration::from_millis(50)).unwrap(), None);
+        assert_eq!(device.read_timeout(4, Duration::from_millis(200)).unwrap(), Some(frame));
+    }
+
+    #[test]
+    fn drop_cleans_the_device() {
+        let device = CanDevice::team_use(1).unwrap();
+        let handle = device.handle;
+
+        drop(device);
+        assert!(hal_stub::can_device(handle).cleaned);
+    }
+
+    #[test]
+    fn stream_ends_after_a_read_error() {
+        let mut stream = CanStream::all(16).unwrap();
+        let message = HAL_CANStreamMessage {
+            messageID: 0x0205_0083,
+            dataSize: 2,
+            ..HAL_CANStreamMessage::default()
+        };
+
+        hal_stub::with_can_stream(stream.session, |state| state.messages.push(message));
+        let frames = stream.by_ref().collect::<Vec<_>>();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].as_ref().unwrap().id, 0x0205_0083);
+
+        hal_stub::with_can_stream(stream.session, |state| state.read_status = HAL_ERR_CANSessionMux_NotInitialized);
+        let frames = stream.by_ref().collect::<Vec<_>>();
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].is_err());
+        assert!(stream.next().is_none());
+        assert_eq!(stream.get_error().unwrap().0, HAL_ERR_CANSessionMux_NotInitialized);
+    }
+}
\ No newline at end of file
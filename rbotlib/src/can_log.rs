@@ -0,0 +1,713 @@
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::can::{CanStream, RawCanFrame};
+
+/// The interface name written to candump logs, readers accept any.
+const CANDUMP_INTERFACE: &str = "can0";
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAP_HEADER_SIZE: usize = 24;
+const PCAP_RECORD_HEADER_SIZE: usize = 16;
+const LINKTYPE_CAN_SOCKETCAN: u32 = 227;
+// A SocketCAN `can_frame`: id and flags, length, 3 bytes of padding and 8 bytes of data.
+const SOCKETCAN_FRAME_SIZE: usize = 16;
+const CAN_EFF_FLAG: u32 = 0x8000_0000;
+const CAN_RTR_FLAG: u32 = 0x4000_0000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CanLogFormat {
+    /// The text format written by `candump -l`, readable by `canplayer`.
+    Candump,
+    /// PCAP with `LINKTYPE_CAN_SOCKETCAN`, readable by Wireshark.
+    Pcap,
+}
+
+impl CanLogFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            CanLogFormat::Candump => "log",
+            CanLogFormat::Pcap => "pcap",
+        }
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Writes frames to any writer in one of the log formats.
+#[derive(Debug)]
+pub struct CanLogWriter<W: Write> {
+    writer: W,
+    format: CanLogFormat,
+    bytes_written: u64,
+}
+
+impl<W: Write> CanLogWriter<W> {
+    /// Creates a writer, writing the file header straight away for formats that have one.
+    pub fn new(writer: W, format: CanLogFormat) -> io::Result<CanLogWriter<W>> {
+        let mut log_writer = CanLogWriter {
+            writer,
+            format,
+            bytes_written: 0,
+        };
+
+        if format == CanLogFormat::Pcap {
+            let mut header = Vec::with_capacity(PCAP_HEADER_SIZE);
+            header.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+            header.extend_from_slice(&2u16.to_le_bytes());
+            header.extend_from_slice(&4u16.to_le_bytes());
+            header.extend_from_slice(&0i32.to_le_bytes());
+            header.extend_from_slice(&0u32.to_le_bytes());
+            header.extend_from_slice(&(SOCKETCAN_FRAME_SIZE as u32).to_le_bytes());
+            header.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_le_bytes());
+
+            log_writer.write_bytes(&header)?;
+        }
+
+        Ok(log_writer)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.bytes_written += bytes.len() as u64;
+        Ok(())
+    }
+
+    pub fn get_format(&self) -> CanLogFormat {
+        self.format
+    }
+
+    /// Gets the number of bytes written so far, including the header.
+    pub fn get_bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn write_frame(&mut self, frame: &RawCanFrame) -> io::Result<()> {
+        match self.format {
+            CanLogFormat::Candump => {
+                let line = format_candump(frame);
+                self.write_bytes(line.as_bytes())
+            }
+            CanLogFormat::Pcap => {
+                let record = format_pcap(frame);
+                self.write_bytes(&record)
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn format_candump(frame: &RawCanFrame) -> String {
+    let timestamp = u64::from(frame.timestamp);
+    let mut line = format!("({}.{:06}) {} ", timestamp / 1000, timestamp % 1000 * 1000, CANDUMP_INTERFACE);
+
+    if frame.is_11bit {
+        let _ = write!(line, "{:03X}#", frame.id & 0x7ff);
+    } else {
+        let _ = write!(line, "{:08X}#", frame.id & 0x1fff_ffff);
+    }
+
+    if frame.is_remote {
+        line.push('R');
+
+        if frame.length > 0 {
+            let _ = write!(line, "{}", frame.length);
+        }
+    } else {
+        for byte in frame.get_data() {
+            let _ = write!(line, "{:02X}", byte);
+        }
+    }
+
+    line.push('\n');
+    line
+}
+
+fn format_pcap(frame: &RawCanFrame) -> Vec<u8> {
+    let timestamp = frame.timestamp;
+    let mut can_id = if frame.is_11bit {
+        frame.id & 0x7ff
+    } else {
+        (frame.id & 0x1fff_ffff) | CAN_EFF_FLAG
+    };
+
+    if frame.is_remote {
+        can_id |= CAN_RTR_FLAG;
+    }
+
+    let mut record = Vec::with_capacity(PCAP_RECORD_HEADER_SIZE + SOCKETCAN_FRAME_SIZE);
+    record.extend_from_slice(&(timestamp / 1000).to_le_bytes());
+    record.extend_from_slice(&(timestamp % 1000 * 1000).to_le_bytes());
+    record.extend_from_slice(&(SOCKETCAN_FRAME_SIZE as u32).to_le_bytes());
+    record.extend_from_slice(&(SOCKETCAN_FRAME_SIZE as u32).to_le_bytes());
+    // SocketCAN headers are in network byte order.
+    record.extend_from_slice(&can_id.to_be_bytes());
+    record.push(frame.length.min(8) as u8);
+    record.extend_from_slice(&[0; 3]);
+    record.extend_from_slice(&frame.data);
+    record
+}
+
+/// Records frames to numbered files in a directory, starting a new file once one reaches
+/// `max_file_size` bytes.
+#[derive(Debug)]
+pub struct CanRecorder {
+    directory: PathBuf,
+    prefix: String,
+    format: CanLogFormat,
+    max_file_size: u64,
+    file_index: u32,
+    writer: CanLogWriter<BufWriter<File>>,
+}
+
+impl CanRecorder {
+    /// Creates `directory` if needed and opens the first `<prefix>-NNN.<extension>` in it that doesn't
+    /// exist yet, so logs from before a restart are never overwritten.
+    pub fn new<P>(directory: P, prefix: &str, format: CanLogFormat, max_file_size: u64) -> io::Result<CanRecorder>
+    where
+        P: AsRef<Path>,
+    {
+        let directory = directory.as_ref().to_path_buf();
+        fs::create_dir_all(&directory)?;
+
+        let (file_index, writer) = CanRecorder::open_file(&directory, prefix, format, 0)?;
+
+        Ok(CanRecorder {
+            directory,
+            prefix: prefix.to_owned(),
+            format,
+            max_file_size,
+            file_index,
+            writer,
+        })
+    }
+
+    fn file_path(directory: &Path, prefix: &str, format: CanLogFormat, index: u32) -> PathBuf {
+        directory.join(format!("{}-{:03}.{}", prefix, index, format.extension()))
+    }
+
+    // Opens the first file from `index` on that doesn't exist, returning its index.
+    fn open_file(
+        directory: &Path,
+        prefix: &str,
+        format: CanLogFormat,
+        mut index: u32,
+    ) -> io::Result<(u32, CanLogWriter<BufWriter<File>>)> {
+        loop {
+            let path = CanRecorder::file_path(directory, prefix, format, index);
+
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(file) => return Ok((index, CanLogWriter::new(BufWriter::new(file), format)?)),
+                Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => index += 1,
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Gets the path of the file currently being written.
+    pub fn get_current_path(&self) -> PathBuf {
+        CanRecorder::file_path(&self.directory, &self.prefix, self.format, self.file_index)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+
+        let next = self.file_index + 1;
+        let (file_index, writer) = CanRecorder::open_file(&self.directory, &self.prefix, self.format, next)?;
+        self.file_index = file_index;
+        self.writer = writer;
+        Ok(())
+    }
+
+    pub fn record(&mut self, frame: &RawCanFrame) -> io::Result<()> {
+        if self.writer.get_bytes_written() >= self.max_file_size {
+            self.rotate()?;
+        }
+
+        self.writer.write_frame(frame)
+    }
+
+    /// Records every frame waiting in `stream`, returning how many were recorded.
+    pub fn record_stream(&mut self, stream: &mut CanStream) -> io::Result<usize> {
+        let mut recorded = 0;
+
+        for frame in stream {
+            self.record(&frame.map_err(io::Error::other)?)?;
+            recorded += 1;
+        }
+
+        Ok(recorded)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for CanRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct PcapLayout {
+    little_endian: bool,
+    nanos: bool,
+    snaplen: u32,
+}
+
+/// Reads frames back from a log in either format, telling them apart by the PCAP magic number.
+#[derive(Debug)]
+pub struct CanLogReader<R: BufRead> {
+    reader: R,
+    pcap: Option<PcapLayout>,
+    line: String,
+}
+
+impl CanLogReader<BufReader<File>> {
+    pub fn open<P>(path: P) -> io::Result<CanLogReader<BufReader<File>>>
+    where
+        P: AsRef<Path>,
+    {
+        CanLogReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: BufRead> CanLogReader<R> {
+    pub fn new(mut reader: R) -> io::Result<CanLogReader<R>> {
+        let mut pcap = {
+            let start = reader.fill_buf()?;
+
+            if start.len() >= 4 {
+                let magic = [start[0], start[1], start[2], start[3]];
+
+                match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                    (PCAP_MAGIC_MICROS, _) => Some(PcapLayout {
+                        little_endian: true,
+                        nanos: false,
+                        snaplen: 0,
+                    }),
+                    (PCAP_MAGIC_NANOS, _) => Some(PcapLayout {
+                        little_endian: true,
+                        nanos: true,
+                        snaplen: 0,
+                    }),
+                    (_, PCAP_MAGIC_MICROS) => Some(PcapLayout {
+                        little_endian: false,
+                        nanos: false,
+                        snaplen: 0,
+                    }),
+                    (_, PCAP_MAGIC_NANOS) => Some(PcapLayout {
+                        little_endian: false,
+                        nanos: true,
+                        snaplen: 0,
+                    }),
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        };
+
+        if let Some(ref mut layout) = pcap {
+            let mut header = [0; PCAP_HEADER_SIZE];
+            reader.read_exact(&mut header)?;
+            layout.snaplen = read_u32(&header[16..20], layout.little_endian);
+
+            let link_type = read_u32(&header[20..24], layout.little_endian);
+            if link_type != LINKTYPE_CAN_SOCKETCAN {
+                return Err(invalid_data("pcap link type is not LINKTYPE_CAN_SOCKETCAN"));
+            }
+        }
+
+        Ok(CanLogReader {
+            reader,
+            pcap,
+            line: String::new(),
+        })
+    }
+
+    pub fn get_format(&self) -> CanLogFormat {
+        if self.pcap.is_some() {
+            CanLogFormat::Pcap
+        } else {
+            CanLogFormat::Candump
+        }
+    }
+
+    fn read_candump(&mut self) -> io::Result<Option<RawCanFrame>> {
+        loop {
+            self.line.clear();
+
+            if self.reader.read_line(&mut self.line)? == 0 {
+                return Ok(None);
+            }
+
+            if !self.line.trim().is_empty() {
+                return parse_candump(self.line.trim()).map(Some);
+            }
+        }
+    }
+
+    fn read_pcap(&mut self, layout: PcapLayout) -> io::Result<Option<RawCanFrame>> {
+        let mut header = [0; PCAP_RECORD_HEADER_SIZE];
+
+        // A clean end of file can only fall between records.
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        self.reader.read_exact(&mut header)?;
+
+        let seconds = read_u32(&header[0..4], layout.little_endian);
+        let fraction = read_u32(&header[4..8], layout.little_endian);
+        let captured = read_u32(&header[8..12], layout.little_endian);
+
+        // Checked before reading so a corrupt length can't make us read or allocate gigabytes.
+        if captured > layout.snaplen {
+            return Err(invalid_data("pcap record is longer than the snapshot length"));
+        }
+
+        let captured = captured as usize;
+
+        if captured > SOCKETCAN_FRAME_SIZE {
+            return Err(invalid_data("pcap record is too long for a CAN frame"));
+        }
+
+        let mut buffer = [0; SOCKETCAN_FRAME_SIZE];
+        let packet = &mut buffer[..captured];
+        self.reader.read_exact(packet)?;
+
+        if captured < 8 {
+            return Err(invalid_data("pcap record is too short for a CAN frame"));
+        }
+
+        let can_id = read_u32(&packet[0..4], false);
+        let length = usize::from(packet[4]).min(8);
+        let mut data = [0; 8];
+        let available = (captured - 8).min(8);
+        data[..available].copy_from_slice(&packet[8..8 + available]);
+
+        let is_11bit = can_id & CAN_EFF_FLAG == 0;
+        let millis = if layout.nanos { fraction / 1_000_000 } else { fraction / 1000 };
+
+        Ok(Some(RawCanFrame {
+            id: if is_11bit { can_id & 0x7ff } else { can_id & 0x1fff_ffff },
+            is_remote: can_id & CAN_RTR_FLAG != 0,
+            is_11bit,
+            data,
+            length,
+            timestamp: seconds.wrapping_mul(1000).wrapping_add(millis),
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for CanLogReader<R> {
+    type Item = io::Result<RawCanFrame>;
+
+    fn next(&mut self) -> Option<io::Result<RawCanFrame>> {
+        let frame = match self.pcap {
+            Some(layout) => self.read_pcap(layout),
+            None => self.read_candump(),
+        };
+
+        frame.transpose()
+    }
+}
+
+fn read_u32(bytes: &[u8], little_endian: bool) -> u32 {
+    let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+    if little_endian {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    }
+}
+
+fn parse_candump(line: &str) -> io::Result<RawCanFrame> {
+    let mut fields = line.split_whitespace();
+    let (timestamp, _interface, frame) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(timestamp), Some(interface), Some(frame)) => (timestamp, interface, frame),
+        _ => return Err(invalid_data("candump line needs a timestamp, interface and frame")),
+    };
+
+    let timestamp = timestamp
+        .strip_prefix('(')
+        .and_then(|timestamp| timestamp.strip_suffix(')'))
+        .ok_or_else(|| invalid_data("candump timestamp is not in parentheses"))?;
+    let (seconds, micros) = match timestamp.find('.') {
+        Some(dot) => (&timestamp[..dot], &timestamp[dot + 1..]),
+        None => (timestamp, "0"),
+    };
+    let seconds: u64 = seconds.parse().map_err(|_| invalid_data("invalid candump timestamp"))?;
+    // Pad or cut the fraction to microseconds, candump always writes six digits.
+    let micros: u64 = format!("{:0<6.6}", micros)
+        .parse()
+        .map_err(|_| invalid_data("invalid candump timestamp"))?;
+
+    let hash = frame.find('#').ok_or_else(|| invalid_data("candump frame has no '#'"))?;
+    let (id, payload) = (&frame[..hash], &frame[hash + 1..]);
+
+    if payload.starts_with('#') {
+        return Err(invalid_data("CAN FD frames are not supported"));
+    }
+
+    let is_11bit = id.len() <= 3;
+    let id = u32::from_str_radix(id, 16).map_err(|_| invalid_data("invalid candump id"))?;
+
+    if id > if is_11bit { 0x7ff } else { 0x1fff_ffff } {
+        return Err(invalid_data("candump id is out of range"));
+    }
+
+    let timestamp = seconds
+        .checked_mul(1000)
+        .and_then(|millis| millis.checked_add(micros / 1000))
+        .ok_or_else(|| invalid_data("candump timestamp is out of range"))?;
+    let mut frame = RawCanFrame {
+        id,
+        is_11bit,
+        // Wraps like the HAL's millisecond timestamps.
+        timestamp: timestamp as u32,
+        ..RawCanFrame::default()
+    };
+
+    if let Some(length) = payload.strip_prefix('R') {
+        frame.is_remote = true;
+        frame.length = if length.is_empty() {
+            0
+        } else {
+            length.parse().map_err(|_| invalid_data("invalid candump remote frame length"))?
+        };
+
+        if frame.length > 8 {
+            return Err(invalid_data("invalid candump remote frame length"));
+        }
+    } else {
+        if payload.len() % 2 != 0 || payload.len() > 16 {
+            return Err(invalid_data("invalid candump data"));
+        }
+
+        for (i, byte) in frame.data.iter_mut().take(payload.len() / 2).enumerate() {
+            *byte = u8::from_str_radix(&payload[i * 2..i * 2 + 2], 16)
+                .map_err(|_| invalid_data("invalid candump data"))?;
+        }
+
+        frame.length = payload.len() / 2;
+    }
+
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<RawCanFrame> {
+        vec![
+            RawCanFrame {
+                id: 0x0205_2c81,
+                data: [0x01, 0x02, 0x03, 0x04, 0xaa, 0xbb, 0xcc, 0xdd],
+                length: 8,
+                timestamp: 1_234_567,
+                ..RawCanFrame::default()
+            },
+            RawCanFrame {
+                id: 0x123,
+                is_11bit: true,
+                data: [0xff, 0, 0, 0, 0, 0, 0, 0],
+                length: 1,
+                timestamp: 1_234_600,
+                ..RawCanFrame::default()
+            },
+            RawCanFrame {
+                id: 0x0a08_0041,
+                is_remote: true,
+                length: 4,
+                timestamp: 1_240_000,
+                ..RawCanFrame::default()
+            },
+            RawCanFrame {
+                id: 0x0a08_0042,
+                length: 0,
+                timestamp: 1_240_001,
+                ..RawCanFrame::default()
+            },
+        ]
+    }
+
+    fn round_trip(format: CanLogFormat) -> Vec<RawCanFrame> {
+        let mut writer = CanLogWriter::new(Vec::new(), format).unwrap();
+
+        for frame in frames().iter() {
+            writer.write_frame(frame).unwrap();
+        }
+
+        let bytes = writer.into_inner();
+        let reader = CanLogReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.get_format(), format);
+        reader.collect::<io::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn candump_format() {
+        let mut writer = CanLogWriter::new(Vec::new(), CanLogFormat::Candump).unwrap();
+
+        for frame in frames().iter() {
+            writer.write_frame(frame).unwrap();
+        }
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "(1234.567000) can0 02052C81#01020304AABBCCDD\n\
+             (1234.600000) can0 123#FF\n\
+             (1240.000000) can0 0A080041#R4\n\
+             (1240.001000) can0 0A080042#\n"
+        );
+    }
+
+    #[test]
+    fn candump_round_trip() {
+        assert_eq!(round_trip(CanLogFormat::Candump), frames());
+    }
+
+    #[test]
+    fn pcap_round_trip() {
+        assert_eq!(round_trip(CanLogFormat::Pcap), frames());
+    }
+
+    #[test]
+    fn pcap_layout() {
+        let mut writer = CanLogWriter::new(Vec::new(), CanLogFormat::Pcap).unwrap();
+        writer.write_frame(&frames()[0]).unwrap();
+        let bytes = writer.into_inner();
+
+        assert_eq!(bytes.len(), PCAP_HEADER_SIZE + PCAP_RECORD_HEADER_SIZE + SOCKETCAN_FRAME_SIZE);
+        assert_eq!(&bytes[0..4], &[0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(&bytes[20..24], &[227, 0, 0, 0]);
+        // Seconds and microseconds of the record.
+        assert_eq!(&bytes[24..28], &1234u32.to_le_bytes());
+        assert_eq!(&bytes[28..32], &567_000u32.to_le_bytes());
+        // Big endian id with the extended frame flag, then the length.
+        assert_eq!(&bytes[40..45], &[0x82, 0x05, 0x2c, 0x81, 8]);
+        assert_eq!(&bytes[48..56], &frames()[0].data);
+    }
+
+    #[test]
+    fn parses_candump_from_other_tools() {
+        let log = "(1436509052.249713) vcan0 1F334455#1122334455667788\n\n(1436509052.25) can1 7FF#R\n";
+        let frames = CanLogReader::new(log.as_bytes())
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].id, 0x1f33_4455);
+        assert_eq!(frames[0].get_data(), &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]);
+        assert_eq!(frames[1].id, 0x7ff);
+        assert!(frames[1].is_11bit && frames[1].is_remote);
+        assert_eq!(frames[1].timestamp, (1_436_509_052_250u64 % (1 << 32)) as u32);
+    }
+
+    #[test]
+    fn rejects_malformed_candump() {
+        let lines = [
+            "garbage",
+            "(1.0) can0 123",
+            "(1.0) can0 123#ABC",
+            "(x) can0 123#",
+            // Seconds that overflow when converted to milliseconds.
+            "(18446744073709551615.000000) can0 123#",
+            "(1.0) can0 123#R9",
+            "(1.0) can0 800#",
+            "(1.0) can0 20000000#",
+        ];
+
+        for line in lines.iter() {
+            let mut reader = CanLogReader::new(line.as_bytes()).unwrap();
+
+            assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_pcap_records() {
+        let mut writer = CanLogWriter::new(Vec::new(), CanLogFormat::Pcap).unwrap();
+        writer.write_frame(&frames()[0]).unwrap();
+        let bytes = writer.into_inner();
+
+        // (snaplen, captured length)
+        let cases = [(16, 17), (16, 0xffff_fff0), (0xffff, 72), (0xffff_ffff, 0xffff_fff0)];
+
+        for &(snaplen, captured) in cases.iter() {
+            let mut corrupt = bytes.clone();
+            corrupt[16..20].copy_from_slice(&u32::to_le_bytes(snaplen));
+            corrupt[PCAP_HEADER_SIZE + 8..PCAP_HEADER_SIZE + 12].copy_from_slice(&u32::to_le_bytes(captured));
+            let mut reader = CanLogReader::new(&corrupt[..]).unwrap();
+
+            assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn recorder_rotates_by_size() {
+        let directory = std::env::temp_dir().join(format!("rbotlib-can-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        {
+            let mut recorder = CanRecorder::new(&directory, "match", CanLogFormat::Pcap, 100).unwrap();
+
+            for frame in frames().iter() {
+                recorder.record(frame).unwrap();
+            }
+
+            assert_eq!(recorder.get_current_path(), directory.join("match-001.pcap"));
+        }
+
+        let mut replayed = Vec::new();
+        for index in 0..2 {
+            let path = directory.join(format!("match-{:03}.pcap", index));
+
+            replayed.extend(CanLogReader::open(path).unwrap().map(Result::unwrap));
+        }
+
+        assert_eq!(replayed, frames());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn recorder_never_overwrites_earlier_logs() {
+        let directory = std::env::temp_dir().join(format!("rbotlib-can-log-restart-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        {
+            let mut recorder = CanRecorder::new(&directory, "match", CanLogFormat::Candump, 1 << 20).unwrap();
+
+            for frame in frames().iter() {
+                recorder.record(frame).unwrap();
+            }
+        }
+
+        let mut recorder = CanRecorder::new(&directory, "match", CanLogFormat::Candump, 1 << 20).unwrap();
+        assert_eq!(recorder.get_current_path(), directory.join("match-001.log"));
+        recorder.record(&frames()[0]).unwrap();
+        drop(recorder);
+
+        let first = CanLogReader::open(directory.join("match-000.log")).unwrap();
+        assert_eq!(first.map(Result::unwrap).collect::<Vec<_>>(), frames());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,373 @@
+use rbothal::*;
+
+use crate::dio::DigitalSource;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl Edge {
+    pub(crate) fn rising(self) -> HAL_Bool {
+        (self != Edge::Falling) as HAL_Bool
+    }
+
+    pub(crate) fn falling(self) -> HAL_Bool {
+        (self != Edge::Rising) as HAL_Bool
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CounterMode {
+    /// Counts edges of the up source up and edges of the down source down.
+    TwoPulse,
+    /// Measures how long the up source stays high, or low when `high` is false.
+    SemiPeriod { high: bool },
+    /// Counts up for pulses shorter than `threshold` seconds and down for longer ones.
+    PulseLength { threshold: f64 },
+    /// Counts edges of the up source, in the direction given by the level of the down source.
+    ExternalDirection,
+}
+
+impl CounterMode {
+    fn hal_mode(self) -> HAL_Counter_Mode::Type {
+        match self {
+            CounterMode::TwoPulse => HAL_Counter_Mode::HAL_Counter_kTwoPulse,
+            CounterMode::SemiPeriod { .. } => HAL_Counter_Mode::HAL_Counter_kSemiperiod,
+            CounterMode::PulseLength { .. } => HAL_Counter_Mode::HAL_Counter_kPulseLength,
+            CounterMode::ExternalDirection => HAL_Counter_Mode::HAL_Counter_kExternalDirection,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CounterBuilder {
+    mode: CounterMode,
+    up_source: Option<(Box<dyn DigitalSource>, Edge)>,
+    down_source: Option<(Box<dyn DigitalSource>, Edge)>,
+    max_period: f64,
+    samples_to_average: Option<i32>,
+    update_when_empty: bool,
+    reverse_direction: bool,
+    distance_per_pulse: f64,
+}
+
+impl Default for CounterBuilder {
+    fn default() -> CounterBuilder {
+        CounterBuilder {
+            mode: CounterMode::TwoPulse,
+            up_source: None,
+            down_source: None,
+            max_period: 0.5,
+            samples_to_average: None,
+            update_when_empty: true,
+            reverse_direction: false,
+            distance_per_pulse: 1.0,
+        }
+    }
+}
+
+impl CounterBuilder {
+    pub fn new() -> CounterBuilder {
+        CounterBuilder::default()
+    }
+
+    pub fn mode(mut self, mode: CounterMode) -> CounterBuilder {
+        self.mode = mode;
+        self
+    }
+
+    pub fn up_source<S>(mut self, source: S, edge: Edge) -> CounterBuilder
+    where
+        S: DigitalSource + 'static,
+    {
+        self.up_source = Some((Box::new(source), edge));
+        self
+    }
+
+    pub fn down_source<S>(mut self, source: S, edge: Edge) -> CounterBuilder
+    where
+        S: DigitalSource + 'static,
+    {
+        self.down_source = Some((Box::new(source), edge));
+        self
+    }
+
+    /// Sets the longest period in seconds before the counter is considered stopped.
+    pub fn max_period(mut self, max_period: f64) -> CounterBuilder {
+        self.max_period = max_period;
+        self
+    }
+
+    pub fn samples_to_average(mut self, samples: i32) -> CounterBuilder {
+        self.samples_to_average = Some(samples);
+        self
+    }
+
+    pub fn update_when_empty(mut self, enabled: bool) -> CounterBuilder {
+        self.update_when_empty = enabled;
+        self
+    }
+
+    pub fn reverse_direction(mut self, reverse_direction: bool) -> CounterBuilder {
+        self.reverse_direction = reverse_direction;
+        self
+    }
+
+    pub fn distance_per_pulse(mut self, distance_per_pulse: f64) -> CounterBuilder {
+        self.distance_per_pulse = distance_per_pulse;
+        self
+    }
+
+    pub fn build(self) -> HalResult<Counter> {
+        let mut index = 0;
+        let handle = hal_call!(HAL_InitializeCounter(self.mode.hal_mode(), &mut index))?;
+        let mut counter = Counter {
+            handle,
+            index,
+            distance_per_pulse: self.distance_per_pulse,
+            up_source: None,
+            down_source: None,
+        };
+
+        if let Some((source, edge)) = self.up_source {
+            // Owned by the counter before routing, so an error drops it after the handle is freed.
+            let source = counter.up_source.get_or_insert(source);
+
+            hal_call!(HAL_SetCounterUpSource(
+                handle,
+                source.get_port_handle_for_routing(),
+                source.get_analog_trigger_type_for_routing(),
+            ))?;
+            hal_call!(HAL_SetCounterUpSourceEdge(handle, edge.rising(), edge.falling()))?;
+        }
+
+        if let Some((source, edge)) = self.down_source {
+            let source = counter.down_source.get_or_insert(source);
+
+            hal_call!(HAL_SetCounterDownSource(
+                handle,
+                source.get_port_handle_for_routing(),
+                source.get_analog_trigger_type_for_routing(),
+            ))?;
+            hal_call!(HAL_SetCounterDownSourceEdge(handle, edge.rising(), edge.falling()))?;
+        }
+
+        match self.mode {
+            CounterMode::TwoPulse => hal_call!(HAL_SetCounterUpDownMode(handle))?,
+            CounterMode::SemiPeriod { high } => hal_call!(HAL_SetCounterSemiPeriodMode(handle, high as HAL_Bool))?,
+            CounterMode::PulseLength { threshold } => hal_call!(HAL_SetCounterPulseLengthMode(handle, threshold))?,
+            CounterMode::ExternalDirection => hal_call!(HAL_SetCounterExternalDirectionMode(handle))?,
+        }
+
+        hal_call!(HAL_SetCounterMaxPeriod(handle, self.max_period))?;
+        hal_call!(HAL_SetCounterUpdateWhenEmpty(handle, self.update_when_empty as HAL_Bool))?;
+
+        if let Some(samples) = self.samples_to_average {
+            hal_call!(HAL_SetCounterSamplesToAverage(handle, samples))?;
+        }
+
+        if self.reverse_direction {
+            hal_call!(HAL_SetCounterReverseDirection(handle, 1))?;
+        }
+
+        Ok(counter)
+    }
+}
+
+#[derive(Debug)]
+pub struct Counter {
+    handle: HAL_CounterHandle,
+    index: i32,
+    distance_per_pulse: f64,
+    // The FPGA keeps reading from these, so they must outlive `handle`.
+    up_source: Option<Box<dyn DigitalSource>>,
+    down_source: Option<Box<dyn DigitalSource>>,
+}
+
+impl Counter {
+    pub fn builder() -> CounterBuilder {
+        CounterBuilder::new()
+    }
+
+    pub fn get_fpga_index(&self) -> i32 {
+        self.index
+    }
+
+    pub fn get(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetCounter(self.handle))
+    }
+
+    pub fn get_distance(&self) -> HalResult<f64> {
+        Ok(f64::from(self.get()?) * self.distance_per_pulse)
+    }
+
+    /// Gets the rate in distance units per second, from the measured period.
+    pub fn get_rate(&self) -> HalResult<f64> {
+        Ok(self.distance_per_pulse / self.get_period()?)
+    }
+
+    pub fn reset(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ResetCounter(self.handle))
+    }
+
+    /// Gets the time in seconds between the last two counts, or the pulse length in semi-period mode.
+    pub fn get_period(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetCounterPeriod(self.handle))
+    }
+
+    pub fn set_max_period(&mut self, max_period: f64) -> HalResult<()> {
+        hal_call!(HAL_SetCounterMaxPeriod(self.handle, max_period))
+    }
+
+    pub fn set_update_when_empty(&mut self, enabled: bool) -> HalResult<()> {
+        hal_call!(HAL_SetCounterUpdateWhenEmpty(self.handle, enabled as HAL_Bool))
+    }
+
+    pub fn get_stopped(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCounterStopped(self.handle))? != 0)
+    }
+
+    pub fn get_direction(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCounterDirection(self.handle))? != 0)
+    }
+
+    pub fn set_reverse_direction(&mut self, reverse_direction: bool) -> HalResult<()> {
+        hal_call!(HAL_SetCounterReverseDirection(self.handle, reverse_direction as HAL_Bool))
+    }
+
+    pub fn set_samples_to_average(&mut self, samples: i32) -> HalResult<()> {
+        hal_call!(HAL_SetCounterSamplesToAverage(self.handle, samples))
+    }
+
+    pub fn get_samples_to_average(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetCounterSamplesToAverage(self.handle))
+    }
+
+    pub fn set_distance_per_pulse(&mut self, distance_per_pulse: f64) {
+        self.distance_per_pulse = distance_per_pulse;
+    }
+}
+
+impl Drop for Counter {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_FreeCounter(self.handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dio::DigitalInput;
+    use crate::hal_stub;
+
+    /// Checks on drop that the counter routed to it was already freed.
+    #[derive(Debug)]
+    struct CheckedSource(HAL_Handle);
+
+    impl DigitalSource for CheckedSource {
+        fn get_port_handle_for_routing(&self) -> HAL_Handle {
+            self.0
+        }
+
+        fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type {
+            HAL_AnalogTriggerType::HAL_Trigger_kInWindow
+        }
+    }
+
+    impl Drop for CheckedSource {
+        fn drop(&mut self) {
+            assert_eq!(hal_stub::live_counters(), 0);
+        }
+    }
+
+    #[test]
+    fn builder_routes_sources_and_settings() {
+        let up = DigitalInput::new(0).unwrap();
+        let down = DigitalInput::new(1).unwrap();
+        let (up_handle, down_handle) = (up.get_port_handle_for_routing(), down.get_port_handle_for_routing());
+
+        let counter = Counter::builder()
+            .up_source(up, Edge::Rising)
+            .down_source(down, Edge::Both)
+            .max_period(0.25)
+            .samples_to_average(4)
+            .update_when_empty(false)
+            .reverse_direction(true)
+            .build()
+            .unwrap();
+        let state = hal_stub::counter(counter.handle);
+
+        assert_eq!(state.mode, HAL_Counter_Mode::HAL_Counter_kTwoPulse);
+        assert_eq!(state.up_source, Some((up_handle, true, false)));
+        assert_eq!(state.down_source, Some((down_handle, true, true)));
+        assert_eq!(state.max_period, 0.25);
+        assert_eq!(state.samples_to_average, 4);
+        assert!(!state.update_when_empty);
+        assert!(state.reverse_direction);
+        assert_eq!(counter.get_fpga_index(), state.index);
+    }
+
+    #[test]
+    fn modes_carry_their_parameters() {
+        let semi_period = Counter::builder().mode(CounterMode::SemiPeriod { high: false }).build().unwrap();
+        let state = hal_stub::counter(semi_period.handle);
+        assert_eq!(state.mode, HAL_Counter_Mode::HAL_Counter_kSemiperiod);
+        assert!(!state.semi_period_high);
+
+        let pulse_length = Counter::builder().mode(CounterMode::PulseLength { threshold: 0.001 }).build().unwrap();
+        let state = hal_stub::counter(pulse_length.handle);
+        assert_eq!(state.mode, HAL_Counter_Mode::HAL_Counter_kPulseLength);
+        assert_eq!(state.pulse_length_threshold, 0.001);
+
+        let external = Counter::builder().mode(CounterMode::ExternalDirection).build().unwrap();
+        assert_eq!(hal_stub::counter(external.handle).mode, HAL_Counter_Mode::HAL_Counter_kExternalDirection);
+        assert_ne!(external.get_fpga_index(), pulse_length.get_fpga_index());
+    }
+
+    #[test]
+    fn failed_build_frees_the_counter_before_its_sources() {
+        let error = Counter::builder()
+            .up_source(CheckedSource(1), Edge::Rising)
+            .down_source(CheckedSource(2), Edge::Rising)
+            .samples_to_average(0)
+            .build()
+            .unwrap_err();
+
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(hal_stub::live_counters(), 0);
+    }
+
+    #[test]
+    fn drop_frees_the_counter_before_its_sources() {
+        let counter = Counter::builder().up_source(CheckedSource(1), Edge::Both).build().unwrap();
+        let handle = counter.handle;
+
+        drop(counter);
+        assert!(hal_stub::counter(handle).freed);
+    }
+
+    #[test]
+    fn distance_and_rate_use_distance_per_pulse() {
+        let mut counter = Counter::builder().distance_per_pulse(0.5).build().unwrap();
+        hal_stub::set_counter(counter.handle, 10, 0.25);
+
+        assert_eq!(counter.get_distance().unwrap(), 5.0);
+        assert_eq!(counter.get_rate().unwrap(), 2.0);
+
+        counter.set_distance_per_pulse(2.0);
+        assert_eq!(counter.get_distance().unwrap(), 20.0);
+        assert_eq!(counter.set_samples_to_average(128).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn all_counters_in_use_is_reported() {
+        let counters: Vec<_> = (0..hal_stub::NUM_COUNTERS).map(|_| Counter::builder().build().unwrap()).collect();
+
+        assert_eq!(Counter::builder().build().unwrap_err().0, NO_AVAILABLE_RESOURCES);
+        drop(counters);
+        assert!(Counter::builder().build().is_ok());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,528 @@
+use std::time::Duration;
+
+use rbothal::*;
+
+use crate::can::{CanDevice, CanDeviceType, CanManufacturer};
+use crate::speed_controller::{forward_speed_controller, SpeedController};
+
+// Frame ids and layouts follow the bitfield structs in WPILib's CanTalonSRX.cpp
+// (allwpilib 2016, hal/lib/Athena/ctre), the last Talon SRX driver published with WPILib.
+// Fields are allocated from the least significant bit of each byte, as GCC lays them out on
+// the roboRIO. Only Talon SRX firmware from that era speaks this protocol, later releases
+// including Phoenix use undocumented frames, as does every Victor SPX.
+const API_CONTROL_1: i32 = 0x000;
+const API_STATUS_1: i32 = 0x050;
+const API_STATUS_2: i32 = 0x051;
+const API_STATUS_4: i32 = 0x053;
+
+// Mode select values of the control frame that aren't setpoints.
+const MODE_FOLLOWER: u8 = 5;
+const MODE_NO_DRIVE: u8 = 15;
+
+// CanTalonSRX sends this limit switch override so the switches follow the settings in flash.
+const LIMIT_SWITCH_USE_FLASH: u8 = 1;
+
+// Percent output is sent as a throttle from -1023 to 1023.
+const THROTTLE_SCALE: f64 = 1023.0;
+const DEMAND_MAX: i32 = (1 << 23) - 1;
+const DEMAND_MIN: i32 = -(1 << 23);
+// Each temperature step is 20/31 of a degree, starting at -50 C.
+const TEMPERATURE_SCALE: f64 = 0.645_161_290_3;
+
+// The controller disables itself if the control frame stops for 100 ms.
+const CONTROL_PERIOD: Duration = Duration::from_millis(10);
+const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ControlMode {
+    /// Output from -1 to 1.
+    PercentOutput = 0,
+    /// Closed loop on the selected sensor, in native sensor units.
+    Position = 1,
+    /// Closed loop on the selected sensor, in native sensor units per 100 ms.
+    Velocity = 2,
+    /// Closed loop on the output current in amps.
+    Current = 3,
+}
+
+impl ControlMode {
+    fn encode_demand(self, value: f64) -> i32 {
+        let demand = match self {
+            ControlMode::PercentOutput => value.clamp(-1.0, 1.0) * THROTTLE_SCALE,
+            ControlMode::Position | ControlMode::Velocity => value,
+            // Sent in milliamps.
+            ControlMode::Current => value * 1000.0,
+        };
+
+        demand.round().clamp(f64::from(DEMAND_MIN), f64::from(DEMAND_MAX)) as i32
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Faults {
+    pub over_temperature: bool,
+    pub under_voltage: bool,
+    pub forward_limit_switch: bool,
+    pub reverse_limit_switch: bool,
+    pub forward_soft_limit: bool,
+    pub reverse_soft_limit: bool,
+    /// Never set in the sticky faults.
+    pub hardware_failure: bool,
+}
+
+impl Faults {
+    pub fn any(self) -> bool {
+        self != Faults::default()
+    }
+}
+
+/// Sent every 10 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status1 {
+    /// In the units of the active closed loop mode.
+    pub closed_loop_error: i32,
+    /// Output from -1 to 1.
+    pub applied_output: f64,
+    pub faults: Faults,
+    pub forward_limit_switch_closed: bool,
+    pub reverse_limit_switch_closed: bool,
+}
+
+/// Sent every 20 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status2 {
+    /// In native sensor units.
+    pub sensor_position: i32,
+    /// In native sensor units per 100 ms.
+    pub sensor_velocity: i32,
+    pub output_current: f64,
+    pub sticky_faults: Faults,
+}
+
+/// Sent every 100 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status4 {
+    /// Controller temperature in degrees Celsius.
+    pub temperature: f64,
+    pub bus_voltage: f64,
+}
+
+fn bit(byte: u8, index: u8) -> bool {
+    byte & (1 << index) != 0
+}
+
+fn read_i24(bytes: &[u8]) -> i32 {
+    // Shifted up and back down to sign extend.
+    (i32::from(bytes[0]) << 24 | i32::from(bytes[1]) << 16 | i32::from(bytes[2]) << 8) >> 8
+}
+
+/// Encodes `TALON_Control_1_General_10ms_t`.
+fn encode_control(mode: u8, demand: i32, profile_slot: u8, reverse_closed_loop: bool) -> [u8; 8] {
+    let mut data = [0; 8];
+
+    // Two token bytes, then the demand as a big endian 24 bit value.
+    data[2..5].copy_from_slice(&demand.to_be_bytes()[1..4]);
+    // ProfileSlotSelect:1, FeedbackDeviceSelect:4 (quadrature encoder), OverrideLimitSwitchEn:3.
+    data[5] = (profile_slot & 0x01) | LIMIT_SWITCH_USE_FLASH << 5;
+    // RevFeedbackSensor:1, RevMotDuringCloseLoopEn:1, OverrideBrakeType:2, ModeSelect:4.
+    data[6] = (reverse_closed_loop as u8) << 1 | (mode & 0x0f) << 4;
+    // RampThrottle is left at zero, no ramp.
+    data
+}
+
+/// Decodes `TALON_Status_1_General_10ms_t`.
+fn decode_status_1(data: &[u8; 8]) -> Status1 {
+    // AppliedThrottle is 11 bits, the high 3 in byte 3 and the low 8 in byte 4.
+    let applied_throttle = (i16::from(data[3] & 0x07) << 8 | i16::from(data[4])) << 5 >> 5;
+
+    Status1 {
+        closed_loop_error: read_i24(&data[0..3]),
+        applied_output: f64::from(applied_throttle) / THROTTLE_SCALE,
+        faults: Faults {
+            over_temperature: bit(data[6], 4),
+            under_voltage: bit(data[6], 3),
+            forward_limit_switch: bit(data[6], 2),
+            reverse_limit_switch: bit(data[6], 1),
+            forward_soft_limit: bit(data[3], 4),
+            reverse_soft_limit: bit(data[3], 3),
+            hardware_failure: bit(data[6], 0),
+        },
+        forward_limit_switch_closed: bit(data[3], 7),
+        reverse_limit_switch_closed: bit(data[3], 6),
+    }
+}
+
+/// Decodes `TALON_Status_2_Feedback_20ms_t`.
+fn decode_status_2(data: &[u8; 8]) -> Status2 {
+    // Current is 10 bits at 0.125 A each, the high 8 in byte 5 and the low 2 on top of byte 6.
+    let output_current = u16::from(data[5]) << 2 | u16::from(data[6] >> 6);
+    let velocity = i32::from(i16::from_be_bytes([data[3], data[4]]));
+    // VelDiv4 means the velocity was sent divided by 4 so it fits in 16 bits.
+    let velocity_scale = if bit(data[7], 5) { 4 } else { 1 };
+
+    Status2 {
+        sensor_position: read_i24(&data[0..3]),
+        sensor_velocity: velocity * velocity_scale,
+        output_current: f64::from(output_current) * 0.125,
+        sticky_faults: Faults {
+            over_temperature: bit(data[6], 5),
+            under_voltage: bit(data[6], 4),
+            forward_limit_switch: bit(data[6], 3),
+            reverse_limit_switch: bit(data[6], 2),
+            forward_soft_limit: bit(data[6], 1),
+            reverse_soft_limit: bit(data[6], 0),
+            hardware_failure: false,
+        },
+    }
+}
+
+/// Decodes `TALON_Status_4_AinTempVbat_100ms_t`.
+fn decode_status_4(data: &[u8; 8]) -> Status4 {
+    // Temperature is 10 bits, the high 8 in byte 5 and the low 2 on top of byte 7.
+    let temperature = u16::from(data[5]) << 2 | u16::from(data[7] >> 6);
+
+    Status4 {
+        temperature: f64::from(temperature) * TEMPERATURE_SCALE - 50.0,
+        // 0.05 V steps above 4 V.
+        bus_voltage: f64::from(data[6]) * 0.05 + 4.0,
+    }
+}
+
+/// The CAN protocol of the Talon SRX, see `TalonSrx`.
+#[derive(Debug)]
+pub struct CtreMotorController {
+    device: CanDevice,
+    percent_output: f64,
+    profile_slot: u8,
+    inverted: bool,
+}
+
+impl CtreMotorController {
+    fn new(device_number: i32) -> HalResult<CtreMotorController> {
+        Ok(CtreMotorController {
+            device: CanDevice::new(CanManufacturer::Ctre, device_number, CanDeviceType::MotorController)?,
+            percent_output: 0.0,
+            profile_slot: 0,
+            inverted: false,
+        })
+    }
+
+    pub fn get_device_number(&self) -> i32 {
+        self.device.get_device_number()
+    }
+
+    fn send_control(&mut self, mode: u8, demand: i32) -> HalResult<()> {
+        let data = encode_control(mode, demand, self.profile_slot, self.inverted);
+
+        self.device.write_packet_repeating(API_CONTROL_1, &data, CONTROL_PERIOD)
+    }
+
+    pub fn set_control(&mut self, mode: ControlMode, value: f64) -> HalResult<()> {
+        // Closed loop modes are reversed by the controller, only the throttle is negated here.
+        let demand = match mode {
+            ControlMode::PercentOutput if self.inverted => -value,
+            _ => value,
+        };
+
+        self.percent_output = if mode == ControlMode::PercentOutput { value } else { 0.0 };
+        self.send_control(mode as u8, mode.encode_demand(demand))
+    }
+
+    /// Selects which of the two sets of closed loop gains are used, from the next setpoint on.
+    pub fn select_profile_slot(&mut self, slot: u8) -> HalResult<()> {
+        if slot > 1 {
+            return Err(HalError(PARAMETER_OUT_OF_RANGE));
+        }
+
+        self.profile_slot = slot;
+        Ok(())
+    }
+
+    /// Mirrors the output of the CTRE controller with `leader_device_number`, until a setpoint
+    /// is set. The protocol can't invert a follower, so `set_inverted` doesn't apply.
+    pub fn follow(&mut self, leader_device_number: i32) -> HalResult<()> {
+        if !(0..64).contains(&leader_device_number) {
+            return Err(HalError(PARAMETER_OUT_OF_RANGE));
+        }
+
+        self.percent_output = 0.0;
+        self.send_control(MODE_FOLLOWER, leader_device_number)
+    }
+
+    fn read_status(&mut self, api_id: i32) -> HalResult<[u8; 8]> {
+        match self.device.read_timeout(api_id, STATUS_TIMEOUT)? {
+            Some(frame) => Ok(frame.data),
+            None => Err(HalError(HAL_CAN_TIMEOUT)),
+        }
+    }
+
+    pub fn get_status_1(&mut self) -> HalResult<Status1> {
+        Ok(decode_status_1(&self.read_status(API_STATUS_1)?))
+    }
+
+    pub fn get_status_2(&mut self) -> HalResult<Status2> {
+        Ok(decode_status_2(&self.read_status(API_STATUS_2)?))
+    }
+
+    pub fn get_status_4(&mut self) -> HalResult<Status4> {
+        Ok(decode_status_4(&self.read_status(API_STATUS_4)?))
+    }
+
+    pub fn get_applied_output(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_1()?.applied_output)
+    }
+
+    pub fn get_closed_loop_error(&mut self) -> HalResult<i32> {
+        Ok(self.get_status_1()?.closed_loop_error)
+    }
+
+    pub fn get_faults(&mut self) -> HalResult<Faults> {
+        Ok(self.get_status_1()?.faults)
+    }
+
+    pub fn get_sticky_faults(&mut self) -> HalResult<Faults> {
+        Ok(self.get_status_2()?.sticky_faults)
+    }
+
+    pub fn get_sensor_position(&mut self) -> HalResult<i32> {
+        Ok(self.get_status_2()?.sensor_position)
+    }
+
+    pub fn get_sensor_velocity(&mut self) -> HalResult<i32> {
+        Ok(self.get_status_2()?.sensor_velocity)
+    }
+
+    pub fn get_output_current(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_2()?.output_current)
+    }
+
+    pub fn get_temperature(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_4()?.temperature)
+    }
+
+    pub fn get_bus_voltage(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_4()?.bus_voltage)
+    }
+}
+
+impl SpeedController for CtreMotorController {
+    fn set(&mut self, speed: f64) -> HalResult<()> {
+        self.set_control(ControlMode::PercentOutput, speed)
+    }
+
+    fn get(&self) -> HalResult<f64> {
+        Ok(self.percent_output)
+    }
+
+    /// Negates percent output and reverses the motor in closed loop modes, from the next
+    /// setpoint on.
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    fn get_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    fn disable(&mut self) -> HalResult<()> {
+        self.percent_output = 0.0;
+        self.send_control(MODE_NO_DRIVE, 0)
+    }
+}
+
+impl Drop for CtreMotorController {
+    fn drop(&mut self) {
+        let _ = self.device.stop_repeating(API_CONTROL_1);
+    }
+}
+
+/// A CTRE Talon SRX controlled over CAN. Only works with firmware from the 2016 season or earlier,
+/// later firmware including Phoenix uses different frames.
+#[derive(Debug)]
+pub struct TalonSrx(CtreMotorController);
+
+impl TalonSrx {
+    pub fn new(device_number: i32) -> HalResult<TalonSrx> {
+        Ok(TalonSrx(CtreMotorController::new(device_number)?))
+    }
+}
+
+forward_speed_controller!(TalonSrx, CtreMotorController);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Field widths of the CanTalonSRX structs in declaration order.
+    const CONTROL_1_FIELDS: [u32; 13] = [8, 8, 8, 8, 8, 1, 4, 3, 1, 1, 2, 4, 8];
+    const STATUS_1_FIELDS: [u32; 20] = [8, 8, 8, 3, 1, 1, 1, 1, 1, 8, 1, 4, 3, 1, 1, 1, 1, 1, 3, 8];
+    const STATUS_2_FIELDS: [u32; 19] = [8, 8, 8, 8, 8, 8, 1, 1, 1, 1, 1, 1, 2, 3, 1, 1, 1, 1, 1];
+    const STATUS_4_FIELDS: [u32; 9] = [8, 8, 8, 8, 8, 8, 8, 6, 2];
+
+    /// Packs bitfield values the way GCC allocates them on a little endian target, giving the
+    /// frame a CanTalonSRX struct with those field values would put on the bus.
+    fn pack(widths: &[u32], values: &[u64]) -> [u8; 8] {
+        assert_eq!(widths.len(), values.len());
+
+        let mut bits = 0u64;
+        let mut offset = 0;
+
+        for (&width, &value) in widths.iter().zip(values.iter()) {
+            assert!(value < 1 << width);
+            bits |= value << offset;
+            offset += width;
+        }
+
+        assert_eq!(offset, 64);
+        bits.to_le_bytes()
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn demands() {
+        let cases = [
+            (ControlMode::PercentOutput, 1.0, 1023),
+            (ControlMode::PercentOutput, -0.5, -512),
+            (ControlMode::PercentOutput, 2.0, 1023),
+            (ControlMode::Position, 4096.0, 4096),
+            (ControlMode::Position, 1e9, DEMAND_MAX),
+            (ControlMode::Velocity, -1e9, DEMAND_MIN),
+            (ControlMode::Current, 12.5, 12500),
+        ];
+
+        for &(mode, value, expected) in cases.iter() {
+            assert_eq!(mode.encode_demand(value), expected, "{:?} {}", mode, value);
+        }
+    }
+
+    #[test]
+    fn control_1_frames() {
+        // (mode, demand, profile slot, reverse closed loop,
+        //  [TokenH, TokenL, DemandH, DemandM, DemandL, ProfileSlotSelect, FeedbackDeviceSelect,
+        //   OverrideLimitSwitchEn, RevFeedbackSensor, RevMotDuringCloseLoopEn, OverrideBrakeType,
+        //   ModeSelect, RampThrottle])
+        let cases = [
+            (0, 1023, 0, false, [0, 0, 0x00, 0x03, 0xff, 0, 0, 1, 0, 0, 0, 0, 0]),
+            (0, -512, 0, false, [0, 0, 0xff, 0xfe, 0x00, 0, 0, 1, 0, 0, 0, 0, 0]),
+            (1, 4096, 1, false, [0, 0, 0x00, 0x10, 0x00, 1, 0, 1, 0, 0, 0, 1, 0]),
+            (2, -300, 0, true, [0, 0, 0xff, 0xfe, 0xd4, 0, 0, 1, 0, 1, 0, 2, 0]),
+            (3, 12500, 0, false, [0, 0, 0x00, 0x30, 0xd4, 0, 0, 1, 0, 0, 0, 3, 0]),
+            (MODE_FOLLOWER, 7, 0, false, [0, 0, 0x00, 0x00, 0x07, 0, 0, 1, 0, 0, 0, 5, 0]),
+            (MODE_NO_DRIVE, 0, 0, false, [0, 0, 0x00, 0x00, 0x00, 0, 0, 1, 0, 0, 0, 15, 0]),
+        ];
+
+        for &(mode, demand, profile_slot, reverse, fields) in cases.iter() {
+            assert_eq!(
+                encode_control(mode, demand, profile_slot, reverse),
+                pack(&CONTROL_1_FIELDS, &fields),
+                "mode {} demand {}",
+                mode,
+                demand
+            );
+        }
+    }
+
+    #[test]
+    fn status_1_frames() {
+        // [CloseLoopErrH, CloseLoopErrM, CloseLoopErrL, AppliedThrottle_h3, Fault_RevSoftLim,
+        //  Fault_ForSoftLim, TokLocked, LimitSwitchClosedRev, LimitSwitchClosedFor,
+        //  AppliedThrottle_l8, ModeSelect_h1, FeedbackDeviceSelect, LimitSwitchEn,
+        //  Fault_HardwareFailure, Fault_RevLim, Fault_ForLim, Fault_UnderVoltage, Fault_OverTemp,
+        //  ModeSelect_b3, TokenSeed]
+        let idle = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        let full_forward = [0, 0x01, 0x00, 0x3, 0, 0, 1, 0, 1, 0xff, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0x5a];
+        let full_reverse = [0xff, 0xff, 0xf6, 0x4, 1, 0, 0, 1, 0, 0x01, 0, 0, 7, 0, 1, 0, 1, 0, 0, 0];
+        let faulted = [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 0, 1, 0, 0];
+
+        let status = decode_status_1(&pack(&STATUS_1_FIELDS, &idle));
+        assert_eq!(status, Status1::default());
+
+        let status = decode_status_1(&pack(&STATUS_1_FIELDS, &full_forward));
+        assert_eq!(status.closed_loop_error, 256);
+        assert_close(status.applied_output, 1.0);
+        assert!(status.forward_limit_switch_closed && !status.reverse_limit_switch_closed);
+        assert!(!status.faults.any());
+
+        let status = decode_status_1(&pack(&STATUS_1_FIELDS, &full_reverse));
+        assert_eq!(status.closed_loop_error, -10);
+        assert_close(status.applied_output, -1.0);
+        assert!(!status.forward_limit_switch_closed && status.reverse_limit_switch_closed);
+        assert_eq!(
+            status.faults,
+            Faults {
+                reverse_soft_limit: true,
+                reverse_limit_switch: true,
+                under_voltage: true,
+                ..Faults::default()
+            }
+        );
+
+        let status = decode_status_1(&pack(&STATUS_1_FIELDS, &faulted));
+        assert_eq!(
+            status.faults,
+            Faults {
+                forward_soft_limit: true,
+                hardware_failure: true,
+                forward_limit_switch: true,
+                over_temperature: true,
+                ..Faults::default()
+            }
+        );
+    }
+
+    #[test]
+    fn status_2_frames() {
+        // ([SensorPositionH, SensorPositionM, SensorPositionL, SensorVelocityH, SensorVelocityL,
+        //   Current_h8, StckyFault_RevSoftLim, StckyFault_ForSoftLim, StckyFault_RevLim,
+        //   StckyFault_ForLim, StckyFault_UnderVoltage, StckyFault_OverTemp, Current_l2,
+        //   reserved, Cal_Done, ProfileSlotSelect, VelDiv4, IsLocal, reserved2],
+        //  position, velocity, current)
+        let cases = [
+            ([0x00, 0x10, 0x00, 0x00, 0x64, 40, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0], 4096, 100, 20.0),
+            ([0xff, 0xf0, 0x00, 0xff, 0x9c, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 0, 1, 0], -4096, -100, 0.125),
+            ([0x7f, 0xff, 0xff, 0x7f, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 3, 0, 1, 1, 1, 1, 0], DEMAND_MAX, 131_068, 127.875),
+        ];
+
+        for &(fields, sensor_position, sensor_velocity, output_current) in cases.iter() {
+            let status = decode_status_2(&pack(&STATUS_2_FIELDS, &fields));
+
+            assert_eq!(status.sensor_position, sensor_position);
+            assert_eq!(status.sensor_velocity, sensor_velocity);
+            assert_close(status.output_current, output_current);
+            assert!(!status.sticky_faults.any());
+        }
+
+        let sticky = [0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            decode_status_2(&pack(&STATUS_2_FIELDS, &sticky)).sticky_faults,
+            Faults {
+                reverse_soft_limit: true,
+                reverse_limit_switch: true,
+                under_voltage: true,
+                ..Faults::default()
+            }
+        );
+    }
+
+    #[test]
+    fn status_4_frames() {
+        // ([AnalogInWithOvH, AnalogInWithOvM, AnalogInWithOvL, AnalogInVelH, AnalogInVelL,
+        //   Temp_h8, BatteryV, reserved1, Temp_l2], temperature, bus voltage)
+        let cases = [
+            ([0, 0, 0, 0, 0, 0, 0, 0, 0], -50.0, 4.0),
+            // Raw temperature 124 is 80 steps of 20/31 C.
+            ([0x01, 0x02, 0x03, 0x04, 0x05, 31, 170, 0, 0], 30.0, 12.5),
+            ([0, 0, 0, 0, 0, 0xff, 0xff, 0x3f, 3], 1023.0 * 20.0 / 31.0 - 50.0, 16.75),
+        ];
+
+        for &(fields, temperature, bus_voltage) in cases.iter() {
+            let status = decode_status_4(&pack(&STATUS_4_FIELDS, &fields));
+
+            assert_close(status.temperature, temperature);
+            assert_close(status.bus_voltage, bus_voltage);
+        }
+    }
+}
\ No newline at end of file
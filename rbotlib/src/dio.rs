@@ -0,0 +1,323 @@
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rbothal::*;
+
+const NUM_GLITCH_FILTERS: usize = 3;
+
+static GLITCH_FILTERS_ALLOCATED: [AtomicBool; NUM_GLITCH_FILTERS] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Anything the FPGA can route as a digital signal into an encoder, counter or interrupt.
+pub trait DigitalSource: Debug + Send {
+    fn get_port_handle_for_routing(&self) -> HAL_Handle;
+
+    fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type;
+}
+
+fn initialize_dio(channel: i32, input: bool) -> HalResult<HAL_DigitalHandle> {
+    if unsafe { HAL_CheckDIOChannel(channel) } == 0 {
+        return Err(HalError(RESOURCE_OUT_OF_RANGE));
+    }
+
+    hal_call!(HAL_InitializeDIOPort(HAL_GetPort(channel), input as HAL_Bool))
+}
+
+#[derive(Debug)]
+pub struct DigitalInput {
+    handle: HAL_DigitalHandle,
+    channel: i32,
+}
+
+impl DigitalInput {
+    pub fn new(channel: i32) -> HalResult<DigitalInput> {
+        Ok(DigitalInput {
+            handle: initialize_dio(channel, true)?,
+            channel,
+        })
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub fn get(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetDIO(self.handle))? != 0)
+    }
+}
+
+impl DigitalSource for DigitalInput {
+    fn get_port_handle_for_routing(&self) -> HAL_Handle {
+        self.handle
+    }
+
+    fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type {
+        HAL_AnalogTriggerType::HAL_Trigger_kInWindow
+    }
+}
+
+impl Drop for DigitalInput {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_FreeDIOPort(self.handle);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DigitalOutput {
+    handle: HAL_DigitalHandle,
+    channel: i32,
+    pwm_generator: Option<HAL_DigitalPWMHandle>,
+}
+
+impl DigitalOutput {
+    pub fn new(channel: i32) -> HalResult<DigitalOutput> {
+        Ok(DigitalOutput {
+            handle: initialize_dio(channel, false)?,
+            channel,
+            pwm_generator: None,
+        })
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub fn set(&mut self, value: bool) -> HalResult<()> {
+        hal_call!(HAL_SetDIO(self.handle, value as HAL_Bool))
+    }
+
+    pub fn get(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetDIO(self.handle))? != 0)
+    }
+
+    /// Outputs a single pulse, `length` is in seconds.
+    pub fn pulse(&mut self, length: f64) -> HalResult<()> {
+        hal_call!(HAL_Pulse(self.handle, length))
+    }
+
+    pub fn is_pulsing(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_IsPulsing(self.handle))? != 0)
+    }
+
+    /// Sets the frequency shared by every digital PWM generator, from 0.6 Hz to 19 kHz.
+    pub fn set_pwm_rate(&mut self, rate: f64) -> HalResult<()> {
+        hal_call!(HAL_SetDigitalPWMRate(rate))
+    }
+
+    pub fn enable_pwm(&mut self, initial_duty_cycle: f64) -> HalResult<()> {
+        if self.pwm_generator.is_some() {
+            return Ok(());
+        }
+
+        let generator = hal_call!(HAL_AllocateDigitalPWM())?;
+        self.pwm_generator = Some(generator);
+
+        hal_call!(HAL_SetDigitalPWMDutyCycle(generator, initial_duty_cycle))?;
+        hal_call!(HAL_SetDigitalPWMOutputChannel(generator, self.channel))
+    }
+
+    pub fn disable_pwm(&mut self) -> HalResult<()> {
+        if let Some(generator) = self.pwm_generator.take() {
+            hal_call!(HAL_SetDigitalPWMOutputChannel(generator, HAL_GetNumDigitalChannels()))?;
+            hal_call!(HAL_FreeDigitalPWM(generator))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn update_duty_cycle(&mut self, duty_cycle: f64) -> HalResult<()> {
+        match self.pwm_generator {
+            Some(generator) => hal_call!(HAL_SetDigitalPWMDutyCycle(generator, duty_cycle)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl DigitalSource for DigitalOutput {
+    fn get_port_handle_for_routing(&self) -> HAL_Handle {
+        self.handle
+    }
+
+    fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type {
+        HAL_AnalogTriggerType::HAL_Trigger_kInWindow
+    }
+}
+
+impl Drop for DigitalOutput {
+    fn drop(&mut self) {
+        let _ = self.disable_pwm();
+
+        unsafe {
+            HAL_FreeDIOPort(self.handle);
+        }
+    }
+}
+
+/// One of the FPGA's three glitch filters, which reject pulses shorter than the filter period
+/// on every input added to it.
+#[derive(Debug)]
+pub struct GlitchFilter {
+    index: usize,
+}
+
+impl GlitchFilter {
+    pub fn new() -> HalResult<GlitchFilter> {
+        for (index, allocated) in GLITCH_FILTERS_ALLOCATED.iter().enumerate() {
+            if allocated.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+                return Ok(GlitchFilter { index });
+            }
+        }
+
+        Err(HalError(NO_AVAILABLE_RESOURCES))
+    }
+
+    pub fn add(&self, input: &DigitalInput) -> HalResult<()> {
+        hal_call!(HAL_SetFilterSelect(input.handle, self.index as i32 + 1))
+    }
+
+    pub fn remove(&self, input: &DigitalInput) -> HalResult<()> {
+        hal_call!(HAL_SetFilterSelect(input.handle, 0))
+    }
+
+    pub fn set_period_cycles(&mut self, cycles: i64) -> HalResult<()> {
+        hal_call!(HAL_SetFilterPeriod(self.index as i32, cycles))
+    }
+
+    pub fn get_period_cycles(&self) -> HalResult<i64> {
+        hal_call!(HAL_GetFilterPeriod(self.index as i32))
+    }
+
+    pub fn set_period_ns(&mut self, nanoseconds: u64) -> HalResult<()> {
+        let ticks_per_us = unsafe { HAL_GetSystemClockTicksPerMicrosecond() } as u64;
+
+        // The filter counts at a quarter of the system clock.
+        self.set_period_cycles((nanoseconds * ticks_per_us / 4 / 1000) as i64)
+    }
+
+    pub fn get_period_ns(&self) -> HalResult<u64> {
+        let ticks_per_us = unsafe { HAL_GetSystemClockTicksPerMicrosecond() } as u64;
+
+        Ok(self.get_period_cycles()? as u64 * 1000 / (ticks_per_us / 4))
+    }
+}
+
+impl Drop for GlitchFilter {
+    fn drop(&mut self) {
+        GLITCH_FILTERS_ALLOCATED[self.index].store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        assert_eq!(DigitalInput::new(-1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(DigitalOutput::new(hal_stub::NUM_DIGITAL_CHANNELS).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn channel_in_use_is_rejected() {
+        let _input = DigitalInput::new(0).unwrap();
+
+        assert_eq!(DigitalOutput::new(0).unwrap_err().0, RESOURCE_IS_ALLOCATED);
+    }
+
+    #[test]
+    fn drop_frees_port() {
+        let handle = DigitalInput::new(1).unwrap().handle;
+
+        assert!(hal_stub::dio(handle).freed);
+        assert!(DigitalOutput::new(1).is_ok());
+    }
+
+    #[test]
+    fn output_drives_the_line() {
+        let mut output = DigitalOutput::new(2).unwrap();
+        assert!(!hal_stub::dio(output.handle).input);
+
+        output.set(true).unwrap();
+        assert!(output.get().unwrap());
+
+        output.pulse(0.001).unwrap();
+        assert_eq!(hal_stub::dio(output.handle).pulse_length, Some(0.001));
+        assert!(output.is_pulsing().unwrap());
+
+        let input = DigitalInput::new(3).unwrap();
+        assert!(hal_stub::dio(input.handle).input);
+        assert!(!input.get().unwrap());
+    }
+
+    #[test]
+    fn pwm_generator_is_routed_and_released() {
+        let mut output = DigitalOutput::new(4).unwrap();
+
+        output.set_pwm_rate(500.0).unwrap();
+        assert_eq!(hal_stub::digital_pwm_rate(), 500.0);
+
+        output.update_duty_cycle(0.75).unwrap();
+        assert!(output.pwm_generator.is_none());
+
+        output.enable_pwm(0.25).unwrap();
+        let generator = output.pwm_generator.unwrap();
+        assert_eq!(hal_stub::digital_pwm(generator).duty_cycle, 0.25);
+        assert_eq!(hal_stub::digital_pwm(generator).output_channel, Some(4));
+
+        // A second enable keeps the generator already driving the line.
+        output.enable_pwm(0.5).unwrap();
+        assert_eq!(output.pwm_generator, Some(generator));
+
+        output.update_duty_cycle(0.75).unwrap();
+        assert_eq!(hal_stub::digital_pwm(generator).duty_cycle, 0.75);
+
+        output.disable_pwm().unwrap();
+        assert_eq!(hal_stub::digital_pwm(generator).output_channel, None);
+        assert!(hal_stub::digital_pwm(generator).freed);
+        assert!(output.pwm_generator.is_none());
+    }
+
+    #[test]
+    fn drop_releases_pwm_generator() {
+        let mut output = DigitalOutput::new(5).unwrap();
+        output.enable_pwm(0.5).unwrap();
+        let generator = output.pwm_generator.unwrap();
+        let handle = output.handle;
+
+        drop(output);
+        assert!(hal_stub::digital_pwm(generator).freed);
+        assert_eq!(hal_stub::digital_pwm(generator).output_channel, None);
+        assert!(hal_stub::dio(handle).freed);
+    }
+
+    #[test]
+    fn glitch_filter_selects_inputs() {
+        let filter = GlitchFilter::new().unwrap();
+        let input = DigitalInput::new(6).unwrap();
+
+        filter.add(&input).unwrap();
+        assert_eq!(hal_stub::dio(input.handle).filter_index, filter.index as i32 + 1);
+
+        filter.remove(&input).unwrap();
+        assert_eq!(hal_stub::dio(input.handle).filter_index, 0);
+    }
+
+    #[test]
+    fn glitch_filter_period_round_trips() {
+        let mut filter = GlitchFilter::new().unwrap();
+
+        // 40 ticks per microsecond counted at a quarter rate is 10 cycles per microsecond.
+        filter.set_period_ns(2_000).unwrap();
+        assert_eq!(filter.get_period_cycles().unwrap(), 20);
+        assert_eq!(filter.get_period_ns().unwrap(), 2_000);
+
+        filter.set_period_cycles(5).unwrap();
+        assert_eq!(filter.get_period_ns().unwrap(), 500);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,251 @@
+use rbothal::*;
+
+use crate::dio::{DigitalInput, DigitalSource};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EncodingType {
+    K1X,
+    K2X,
+    K4X,
+}
+
+impl From<EncodingType> for HAL_EncoderEncodingType::Type {
+    fn from(encoding: EncodingType) -> HAL_EncoderEncodingType::Type {
+        match encoding {
+            EncodingType::K1X => HAL_EncoderEncodingType::HAL_Encoder_k1X,
+            EncodingType::K2X => HAL_EncoderEncodingType::HAL_Encoder_k2X,
+            EncodingType::K4X => HAL_EncoderEncodingType::HAL_Encoder_k4X,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IndexingType {
+    ResetWhileHigh,
+    ResetWhileLow,
+    ResetOnFallingEdge,
+    ResetOnRisingEdge,
+}
+
+impl From<IndexingType> for HAL_EncoderIndexingType::Type {
+    fn from(indexing: IndexingType) -> HAL_EncoderIndexingType::Type {
+        match indexing {
+            IndexingType::ResetWhileHigh => HAL_EncoderIndexingType::HAL_kResetWhileHigh,
+            IndexingType::ResetWhileLow => HAL_EncoderIndexingType::HAL_kResetWhileLow,
+            IndexingType::ResetOnFallingEdge => HAL_EncoderIndexingType::HAL_kResetOnFallingEdge,
+            IndexingType::ResetOnRisingEdge => HAL_EncoderIndexingType::HAL_kResetOnRisingEdge,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Encoder {
+    handle: HAL_EncoderHandle,
+    // The FPGA keeps reading from these, so they must outlive `handle`.
+    _source_a: Box<dyn DigitalSource>,
+    _source_b: Box<dyn DigitalSource>,
+    index_source: Option<Box<dyn DigitalSource>>,
+}
+
+impl Encoder {
+    pub fn new(channel_a: i32, channel_b: i32, reverse_direction: bool, encoding: EncodingType) -> HalResult<Encoder> {
+        Encoder::from_sources(
+            DigitalInput::new(channel_a)?,
+            DigitalInput::new(channel_b)?,
+            reverse_direction,
+            encoding,
+        )
+    }
+
+    pub fn from_sources<A, B>(source_a: A, source_b: B, reverse_direction: bool, encoding: EncodingType) -> HalResult<Encoder>
+    where
+        A: DigitalSource + 'static,
+        B: DigitalSource + 'static,
+    {
+        let handle = hal_call!(HAL_InitializeEncoder(
+            source_a.get_port_handle_for_routing(),
+            source_a.get_analog_trigger_type_for_routing(),
+            source_b.get_port_handle_for_routing(),
+            source_b.get_analog_trigger_type_for_routing(),
+            reverse_direction as HAL_Bool,
+            encoding.into(),
+        ))?;
+
+        Ok(Encoder {
+            handle,
+            _source_a: Box::new(source_a),
+            _source_b: Box::new(source_b),
+            index_source: None,
+        })
+    }
+
+    /// Gets the count scaled by the encoding type, so a 4x encoder still counts once per cycle.
+    pub fn get(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetEncoder(self.handle))
+    }
+
+    pub fn get_raw(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetEncoderRaw(self.handle))
+    }
+
+    pub fn get_encoding_scale(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetEncoderEncodingScale(self.handle))
+    }
+
+    pub fn reset(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ResetEncoder(self.handle))
+    }
+
+    pub fn get_period(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetEncoderPeriod(self.handle))
+    }
+
+    pub fn set_max_period(&mut self, max_period: f64) -> HalResult<()> {
+        hal_call!(HAL_SetEncoderMaxPeriod(self.handle, max_period))
+    }
+
+    pub fn get_stopped(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetEncoderStopped(self.handle))? != 0)
+    }
+
+    pub fn get_direction(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetEncoderDirection(self.handle))? != 0)
+    }
+
+    pub fn get_distance(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetEncoderDistance(self.handle))
+    }
+
+    /// Gets the rate in distance units per second.
+    pub fn get_rate(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetEncoderRate(self.handle))
+    }
+
+    pub fn set_min_rate(&mut self, min_rate: f64) -> HalResult<()> {
+        hal_call!(HAL_SetEncoderMinRate(self.handle, min_rate))
+    }
+
+    pub fn set_distance_per_pulse(&mut self, distance_per_pulse: f64) -> HalResult<()> {
+        hal_call!(HAL_SetEncoderDistancePerPulse(self.handle, distance_per_pulse))
+    }
+
+    pub fn get_distance_per_pulse(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetEncoderDistancePerPulse(self.handle))
+    }
+
+    pub fn set_reverse_direction(&mut self, reverse_direction: bool) -> HalResult<()> {
+        hal_call!(HAL_SetEncoderReverseDirection(self.handle, reverse_direction as HAL_Bool))
+    }
+
+    /// Sets how many samples are averaged for the period, from 1 to 127.
+    pub fn set_samples_to_average(&mut self, samples: i32) -> HalResult<()> {
+        hal_call!(HAL_SetEncoderSamplesToAverage(self.handle, samples))
+    }
+
+    pub fn get_samples_to_average(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetEncoderSamplesToAverage(self.handle))
+    }
+
+    pub fn set_index_channel(&mut self, channel: i32, indexing: IndexingType) -> HalResult<()> {
+        self.set_index_source(DigitalInput::new(channel)?, indexing)
+    }
+
+    pub fn set_index_source<S>(&mut self, source: S, indexing: IndexingType) -> HalResult<()>
+    where
+        S: DigitalSource + 'static,
+    {
+        hal_call!(HAL_SetEncoderIndexSource(
+            self.handle,
+            source.get_port_handle_for_routing(),
+            source.get_analog_trigger_type_for_routing(),
+            indexing.into(),
+        ))?;
+
+        self.index_source = Some(Box::new(source));
+        Ok(())
+    }
+
+    pub fn get_fpga_index(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetEncoderFPGAIndex(self.handle))
+    }
+
+    pub fn get_decoding_scale_factor(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetEncoderDecodingScaleFactor(self.handle))
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_FreeEncoder(self.handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        let error = Encoder::new(0, hal_stub::NUM_DIGITAL_CHANNELS, false, EncodingType::K4X).unwrap_err();
+
+        assert_eq!(error.0, RESOURCE_OUT_OF_RANGE);
+        // The input already opened for channel A is released again.
+        assert!(DigitalInput::new(0).is_ok());
+    }
+
+    #[test]
+    fn sources_live_as_long_as_the_encoder() {
+        let a = DigitalInput::new(0).unwrap().get_port_handle_for_routing();
+        let b = DigitalInput::new(1).unwrap().get_port_handle_for_routing();
+
+        let encoder = Encoder::new(0, 1, true, EncodingType::K2X).unwrap();
+        let state = hal_stub::encoder(encoder.handle);
+        assert_eq!((state.source_a, state.source_b), (a, b));
+        assert!(state.reverse_direction);
+        assert_eq!(state.encoding, HAL_EncoderEncodingType::HAL_Encoder_k2X);
+        assert!(!hal_stub::dio(a).freed && !hal_stub::dio(b).freed);
+
+        let handle = encoder.handle;
+        drop(encoder);
+        assert!(hal_stub::encoder(handle).freed);
+        assert!(hal_stub::dio(a).freed && hal_stub::dio(b).freed);
+    }
+
+    #[test]
+    fn encoding_scale_follows_encoding() {
+        for (encoding, scale) in [(EncodingType::K1X, 1), (EncodingType::K2X, 2), (EncodingType::K4X, 4)].iter() {
+            let encoder = Encoder::new(2, 3, false, *encoding).unwrap();
+
+            assert_eq!(encoder.get_encoding_scale().unwrap(), *scale);
+        }
+    }
+
+    #[test]
+    fn out_of_range_settings_are_rejected() {
+        let mut encoder = Encoder::new(4, 5, false, EncodingType::K4X).unwrap();
+
+        assert_eq!(encoder.set_samples_to_average(0).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(encoder.set_samples_to_average(128).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        encoder.set_samples_to_average(127).unwrap();
+        assert_eq!(encoder.get_samples_to_average().unwrap(), 127);
+
+        assert_eq!(encoder.set_distance_per_pulse(0.0).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        encoder.set_distance_per_pulse(0.05).unwrap();
+        assert_eq!(encoder.get_distance_per_pulse().unwrap(), 0.05);
+    }
+
+    #[test]
+    fn index_channel_is_routed() {
+        let mut encoder = Encoder::new(6, 7, false, EncodingType::K4X).unwrap();
+
+        encoder.set_index_channel(8, IndexingType::ResetOnRisingEdge).unwrap();
+        let index = encoder.index_source.as_ref().unwrap().get_port_handle_for_routing();
+        assert_eq!(
+            hal_stub::encoder(encoder.handle).index_source,
+            Some((index, HAL_EncoderIndexingType::HAL_kResetOnRisingEdge))
+        );
+
+        assert!(encoder.set_index_channel(6, IndexingType::ResetWhileHigh).is_err());
+    }
+}
\ No newline at end of file
@@ -1,3 +1,8 @@
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
 use rbothal::*;
 
 pub fn get_version() -> HalResult<i32> {
@@ -14,4 +19,414 @@ pub fn get_time_us() -> HalResult<u64> {
 
 pub fn get_user_down() -> HalResult<bool> {
     Ok(hal_call!(HAL_GetFPGAButton())? != 0)
+}
+
+/// Expands a 32 bit timestamp, like those from interrupts and auto SPI, to the nearest full FPGA
+/// time before now.
+pub fn expand_time(lower: u32) -> HalResult<FpgaInstant> {
+    FpgaClock.expand(lower)
+}
+
+/// A span of FPGA time with microsecond resolution.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FpgaDuration(u64);
+
+impl FpgaDuration {
+    pub const ZERO: FpgaDuration = FpgaDuration(0);
+
+    pub fn from_micros(micros: u64) -> FpgaDuration {
+        FpgaDuration(micros)
+    }
+
+    pub fn from_millis(millis: u64) -> FpgaDuration {
+        FpgaDuration(millis * 1000)
+    }
+
+    pub fn from_secs_f64(secs: f64) -> FpgaDuration {
+        FpgaDuration((secs * 1e6).round() as u64)
+    }
+
+    pub fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    pub fn as_secs_f64(self) -> f64 {
+        self.0 as f64 / 1e6
+    }
+
+    pub fn checked_sub(self, other: FpgaDuration) -> Option<FpgaDuration> {
+        self.0.checked_sub(other.0).map(FpgaDuration)
+    }
+
+    pub fn saturating_sub(self, other: FpgaDuration) -> FpgaDuration {
+        FpgaDuration(self.0.saturating_sub(other.0))
+    }
+}
+
+impl From<Duration> for FpgaDuration {
+    fn from(duration: Duration) -> FpgaDuration {
+        FpgaDuration(duration.as_micros() as u64)
+    }
+}
+
+impl From<FpgaDuration> for Duration {
+    fn from(duration: FpgaDuration) -> Duration {
+        Duration::from_micros(duration.0)
+    }
+}
+
+impl Add for FpgaDuration {
+    type Output = FpgaDuration;
+
+    fn add(self, other: FpgaDuration) -> FpgaDuration {
+        FpgaDuration(self.0 + other.0)
+    }
+}
+
+impl AddAssign for FpgaDuration {
+    fn add_assign(&mut self, other: FpgaDuration) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for FpgaDuration {
+    type Output = FpgaDuration;
+
+    fn sub(self, other: FpgaDuration) -> FpgaDuration {
+        FpgaDuration(self.0 - other.0)
+    }
+}
+
+impl SubAssign for FpgaDuration {
+    fn sub_assign(&mut self, other: FpgaDuration) {
+        self.0 -= other.0;
+    }
+}
+
+/// A point in FPGA time, in microseconds since the FPGA started. Unlike the system clock it never
+/// jumps, so it is safe to measure loop timing with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FpgaInstant(u64);
+
+impl FpgaInstant {
+    pub fn now() -> HalResult<FpgaInstant> {
+        FpgaClock.now()
+    }
+
+    pub fn from_micros(micros: u64) -> FpgaInstant {
+        FpgaInstant(micros)
+    }
+
+    pub fn as_micros(self) -> u64 {
+        self.0
+    }
+
+    /// Gets the time since `earlier`, or zero if `earlier` is later than this.
+    pub fn duration_since(self, earlier: FpgaInstant) -> FpgaDuration {
+        FpgaDuration(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn checked_duration_since(self, earlier: FpgaInstant) -> Option<FpgaDuration> {
+        self.0.checked_sub(earlier.0).map(FpgaDuration)
+    }
+
+    /// Expands the lower 32 bits of a time to the latest full time at or before this one, or `None`
+    /// if no such time exists because this one is too close to boot.
+    pub fn expand(self, lower: u32) -> Option<FpgaInstant> {
+        let mut upper = self.0 & !0xffff_ffff;
+
+        // A lower half ahead of ours means the counter has wrapped since the timestamp was taken.
+        if u64::from(lower) > self.0 & 0xffff_ffff {
+            upper = upper.checked_sub(1 << 32)?;
+        }
+
+        Some(FpgaInstant(upper + u64::from(lower)))
+    }
+}
+
+impl Add<FpgaDuration> for FpgaInstant {
+    type Output = FpgaInstant;
+
+    fn add(self, duration: FpgaDuration) -> FpgaInstant {
+        FpgaInstant(self.0 + duration.0)
+    }
+}
+
+impl AddAssign<FpgaDuration> for FpgaInstant {
+    fn add_assign(&mut self, duration: FpgaDuration) {
+        self.0 += duration.0;
+    }
+}
+
+impl Sub<FpgaDuration> for FpgaInstant {
+    type Output = FpgaInstant;
+
+    fn sub(self, duration: FpgaDuration) -> FpgaInstant {
+        FpgaInstant(self.0 - duration.0)
+    }
+}
+
+impl Sub for FpgaInstant {
+    type Output = FpgaDuration;
+
+    fn sub(self, earlier: FpgaInstant) -> FpgaDuration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A source of FPGA time, so timing code can be driven by a `ManualClock` in tests.
+pub trait Clock {
+    fn now(&self) -> HalResult<FpgaInstant>;
+
+    /// Fails with `PARAMETER_OUT_OF_RANGE` for a timestamp that would be from before boot.
+    fn expand(&self, lower: u32) -> HalResult<FpgaInstant> {
+        self.now()?.expand(lower).ok_or(HalError(PARAMETER_OUT_OF_RANGE))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FpgaClock;
+
+impl Clock for FpgaClock {
+    fn now(&self) -> HalResult<FpgaInstant> {
+        Ok(FpgaInstant(get_time_us()?))
+    }
+
+    fn expand(&self, lower: u32) -> HalResult<FpgaInstant> {
+        Ok(FpgaInstant(hal_call!(HAL_ExpandFPGATime(lower))?))
+    }
+}
+
+/// A clock that only moves when told to, clones share the same time.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(start: FpgaInstant) -> ManualClock {
+        ManualClock {
+            now: Arc::new(AtomicU64::new(start.0)),
+        }
+    }
+
+    pub fn set(&self, now: FpgaInstant) {
+        self.now.store(now.0, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, duration: FpgaDuration) {
+        self.now.fetch_add(duration.0, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> HalResult<FpgaInstant> {
+        Ok(FpgaInstant(self.now.load(Ordering::SeqCst)))
+    }
+}
+
+/// A stopwatch over FPGA time.
+#[derive(Debug)]
+pub struct Timer<C: Clock = FpgaClock> {
+    clock: C,
+    start: FpgaInstant,
+    accumulated: FpgaDuration,
+    running: bool,
+}
+
+impl Timer<FpgaClock> {
+    pub fn new() -> Timer<FpgaClock> {
+        Timer::with_clock(FpgaClock)
+    }
+}
+
+impl Default for Timer<FpgaClock> {
+    fn default() -> Timer<FpgaClock> {
+        Timer::new()
+    }
+}
+
+impl<C: Clock> Timer<C> {
+    /// Creates a stopped timer reading zero.
+    pub fn with_clock(clock: C) -> Timer<C> {
+        Timer {
+            clock,
+            start: FpgaInstant(0),
+            accumulated: FpgaDuration::ZERO,
+            running: false,
+        }
+    }
+
+    /// Gets the total time the timer has been running since the last reset.
+    pub fn get(&self) -> HalResult<FpgaDuration> {
+        if self.running {
+            Ok(self.accumulated + self.clock.now()?.duration_since(self.start))
+        } else {
+            Ok(self.accumulated)
+        }
+    }
+
+    pub fn reset(&mut self) -> HalResult<()> {
+        self.accumulated = FpgaDuration::ZERO;
+        self.start = self.clock.now()?;
+        Ok(())
+    }
+
+    /// Starts the timer, keeping any time already accumulated. Does nothing if already running.
+    pub fn start(&mut self) -> HalResult<()> {
+        if !self.running {
+            self.start = self.clock.now()?;
+            self.running = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> HalResult<()> {
+        self.accumulated = self.get()?;
+        self.running = false;
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn has_elapsed(&self, period: FpgaDuration) -> HalResult<bool> {
+        Ok(self.get()? >= period)
+    }
+
+    /// Checks if `period` has elapsed and if so moves the start forward by one period, keeping
+    /// periodic work in phase even when a check comes late.
+    pub fn advance_if_elapsed(&mut self, period: FpgaDuration) -> HalResult<bool> {
+        if !self.has_elapsed(period)? {
+            return Ok(false);
+        }
+
+        // Time from before the last stop is used up first, since moving the start past now would
+        // saturate the running part to zero.
+        let from_accumulated = period.min(self.accumulated);
+        self.accumulated -= from_accumulated;
+
+        if self.running {
+            self.start += period - from_accumulated;
+        }
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instant_arithmetic() {
+        let start = FpgaInstant::from_micros(1_000);
+        let later = start + FpgaDuration::from_millis(20);
+
+        assert_eq!(later.as_micros(), 21_000);
+        assert_eq!(later - start, FpgaDuration::from_micros(20_000));
+        assert_eq!(start - later, FpgaDuration::ZERO);
+        assert_eq!(start.checked_duration_since(later), None);
+        assert_eq!(later - FpgaDuration::from_secs_f64(0.02), start);
+        assert_eq!(FpgaDuration::from(Duration::from_millis(5)).as_secs_f64(), 0.005);
+    }
+
+    #[test]
+    fn expands_32_bit_timestamps() {
+        let cases = [
+            // (now, lower, expanded)
+            (0x0000_0000_0000_1000, 0x0000_0800, 0x0000_0000_0000_0800),
+            (0x0000_0003_0000_1000, 0x0000_1000, 0x0000_0003_0000_1000),
+            (0x0000_0003_0000_1000, 0xffff_f000, 0x0000_0002_ffff_f000),
+            (0x0000_0001_0000_1000, 0xffff_f000, 0x0000_0000_ffff_f000),
+        ];
+
+        for &(now, lower, expanded) in cases.iter() {
+            let clock = ManualClock::new(FpgaInstant::from_micros(now));
+
+            assert_eq!(clock.expand(lower).unwrap(), FpgaInstant::from_micros(expanded));
+        }
+    }
+
+    #[test]
+    fn expand_near_boot_is_never_in_the_future() {
+        let now = FpgaInstant::from_micros(0x1000);
+
+        assert_eq!(now.expand(0xffff_f000), None);
+        assert_eq!(now.expand(0x1000), Some(now));
+
+        let clock = ManualClock::new(now);
+        assert_eq!(clock.expand(0xffff_f000).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn timer_accumulates_while_running() {
+        let clock = ManualClock::new(FpgaInstant::from_micros(5_000));
+        let mut timer = Timer::with_clock(clock.clone());
+
+        clock.advance(FpgaDuration::from_millis(10));
+        assert_eq!(timer.get().unwrap(), FpgaDuration::ZERO);
+
+        timer.start().unwrap();
+        clock.advance(FpgaDuration::from_millis(10));
+        timer.stop().unwrap();
+        clock.advance(FpgaDuration::from_millis(10));
+        assert_eq!(timer.get().unwrap(), FpgaDuration::from_millis(10));
+
+        timer.start().unwrap();
+        clock.advance(FpgaDuration::from_millis(5));
+        assert_eq!(timer.get().unwrap(), FpgaDuration::from_millis(15));
+        assert!(timer.has_elapsed(FpgaDuration::from_millis(15)).unwrap());
+
+        timer.reset().unwrap();
+        assert_eq!(timer.get().unwrap(), FpgaDuration::ZERO);
+        assert!(timer.is_running());
+    }
+
+    #[test]
+    fn advance_if_elapsed_keeps_phase() {
+        let clock = ManualClock::new(FpgaInstant::from_micros(0));
+        let mut timer = Timer::with_clock(clock.clone());
+        let period = FpgaDuration::from_millis(20);
+
+        timer.start().unwrap();
+        clock.advance(FpgaDuration::from_millis(19));
+        assert!(!timer.advance_if_elapsed(period).unwrap());
+
+        clock.advance(FpgaDuration::from_millis(6));
+        assert!(timer.advance_if_elapsed(period).unwrap());
+        assert_eq!(timer.get().unwrap(), FpgaDuration::from_millis(5));
+        assert!(!timer.advance_if_elapsed(period).unwrap());
+
+        timer.stop().unwrap();
+        clock.advance(FpgaDuration::from_millis(100));
+        assert!(!timer.advance_if_elapsed(period).unwrap());
+    }
+
+    #[test]
+    fn advance_if_elapsed_after_restart() {
+        let clock = ManualClock::new(FpgaInstant::from_micros(0));
+        let mut timer = Timer::with_clock(clock.clone());
+        let period = FpgaDuration::from_millis(20);
+
+        timer.start().unwrap();
+        clock.advance(FpgaDuration::from_millis(30));
+        timer.stop().unwrap();
+        timer.start().unwrap();
+        clock.advance(FpgaDuration::from_millis(5));
+
+        // 30 ms accumulated before the restart and 5 ms since.
+        assert!(timer.advance_if_elapsed(period).unwrap());
+        assert_eq!(timer.get().unwrap(), FpgaDuration::from_millis(15));
+        assert!(!timer.advance_if_elapsed(period).unwrap());
+
+        clock.advance(FpgaDuration::from_millis(25));
+        assert!(timer.advance_if_elapsed(period).unwrap());
+        assert_eq!(timer.get().unwrap(), FpgaDuration::from_millis(20));
+        assert!(timer.advance_if_elapsed(period).unwrap());
+        assert_eq!(timer.get().unwrap(), FpgaDuration::ZERO);
+        assert!(!timer.advance_if_elapsed(period).unwrap());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+use rbothal::*;
+
+pub trait Gyro {
+    /// Measures the drift while the robot is still, blocking until it is done.
+    fn calibrate(&mut self) -> HalResult<()>;
+
+    fn reset(&mut self) -> HalResult<()>;
+
+    /// Gets the heading in degrees, clockwise positive and continuous past 360.
+    fn get_angle(&self) -> HalResult<f64>;
+
+    /// Gets the rate of rotation in degrees per second.
+    fn get_rate(&self) -> HalResult<f64>;
+}
\ No newline at end of file
@@ -0,0 +1,2620 @@
+//! Link-level stand-ins for the HAL so unit tests can run off-robot.
+//!
+//! Every function here is exported under the same symbol as the real HAL, so the `rbothal`
+//! bindings resolve to it when the test binary is built for the host. State is thread local
+//! because each test runs on its own thread.
+#![allow(non_snake_case)]
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::ffi::CStr;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::raw::{c_char, c_int, c_short, c_ulong, c_void};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+
+use rbothal::*;
+
+use crate::pwm::PwmBounds;
+
+pub const NUM_PWM_CHANNELS: i32 = 20;
+
+#[derive(Clone, Debug, Default)]
+pub struct PwmState {
+    pub config: Option<PwmBounds>,
+    pub raw: i32,
+    pub speed: f64,
+    pub position: f64,
+    pub eliminate_deadband: bool,
+    pub period_scale: i32,
+    pub zero_latched: bool,
+    pub disabled: bool,
+    pub freed: bool,
+}
+
+thread_local! {
+    static PWMS: RefCell<HashMap<HAL_DigitalHandle, PwmState>> = RefCell::new(HashMap::new());
+}
+
+pub fn pwm(handle: HAL_DigitalHandle) -> PwmState {
+    PWMS.with(|pwms| pwms.borrow()[&handle].clone())
+}
+
+fn with_pwm<T>(handle: HAL_DigitalHandle, status: *mut i32, f: impl FnOnce(&mut PwmState) -> T) -> T
+where
+    T: Default,
+{
+    PWMS.with(|pwms| match pwms.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetErrorMessage(_code: i32) -> *const c_char {
+    b"HAL: stubbed error\0".as_ptr() as *const c_char
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPort(channel: i32) -> HAL_PortHandle {
+    channel
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializePWMPort(port: HAL_PortHandle, status: *mut i32) -> HAL_DigitalHandle {
+    if !(0..NUM_PWM_CHANNELS).contains(&port) {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    let handle = port + 1;
+
+    PWMS.with(|pwms| {
+        let mut pwms = pwms.borrow_mut();
+
+        if let Some(PwmState { freed: false, .. }) = pwms.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        pwms.insert(handle, PwmState::default());
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreePWMPort(handle: HAL_DigitalHandle, status: *mut i32) {
+    with_pwm(handle, status, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMConfig(
+    handle: HAL_DigitalHandle,
+    max: f64,
+    deadband_max: f64,
+    center: f64,
+    deadband_min: f64,
+    min: f64,
+    status: *mut i32,
+) {
+    with_pwm(handle, status, |state| {
+        state.config = Some(PwmBounds {
+            max,
+            deadband_max,
+            center,
+            deadband_min,
+            min,
+        })
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMEliminateDeadband(handle: HAL_DigitalHandle, eliminate: HAL_Bool, status: *mut i32) {
+    with_pwm(handle, status, |state| state.eliminate_deadband = eliminate != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPWMEliminateDeadband(handle: HAL_DigitalHandle, status: *mut i32) -> HAL_Bool {
+    with_pwm(handle, status, |state| state.eliminate_deadband as HAL_Bool)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMRaw(handle: HAL_DigitalHandle, value: i32, status: *mut i32) {
+    with_pwm(handle, status, |state| {
+        state.raw = value;
+        state.disabled = false;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPWMRaw(handle: HAL_DigitalHandle, status: *mut i32) -> i32 {
+    with_pwm(handle, status, |state| state.raw)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMSpeed(handle: HAL_DigitalHandle, speed: f64, status: *mut i32) {
+    with_pwm(handle, status, |state| {
+        state.speed = speed;
+        state.disabled = false;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPWMSpeed(handle: HAL_DigitalHandle, status: *mut i32) -> f64 {
+    with_pwm(handle, status, |state| if state.disabled { 0.0 } else { state.speed })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMPosition(handle: HAL_DigitalHandle, position: f64, status: *mut i32) {
+    with_pwm(handle, status, |state| {
+        state.position = position;
+        state.disabled = false;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPWMPosition(handle: HAL_DigitalHandle, status: *mut i32) -> f64 {
+    with_pwm(handle, status, |state| state.position)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMDisabled(handle: HAL_DigitalHandle, status: *mut i32) {
+    with_pwm(handle, status, |state| state.disabled = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_LatchPWMZero(handle: HAL_DigitalHandle, status: *mut i32) {
+    with_pwm(handle, status, |state| state.zero_latched = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetPWMPeriodScale(handle: HAL_DigitalHandle, squelch_mask: i32, status: *mut i32) {
+    with_pwm(handle, status, |state| state.period_scale = squelch_mask)
+}
+
+pub const NUM_DIGITAL_CHANNELS: i32 = 31;
+pub const NUM_DIGITAL_PWM_OUTPUTS: usize = 6;
+
+#[derive(Clone, Debug, Default)]
+pub struct DioState {
+    pub input: bool,
+    pub value: bool,
+    pub pulse_length: Option<f64>,
+    pub filter_index: i32,
+    pub freed: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DigitalPwmState {
+    pub duty_cycle: f64,
+    pub output_channel: Option<i32>,
+    pub freed: bool,
+}
+
+thread_local! {
+    static DIOS: RefCell<HashMap<HAL_DigitalHandle, DioState>> = RefCell::new(HashMap::new());
+    static DIGITAL_PWMS: RefCell<HashMap<HAL_DigitalPWMHandle, DigitalPwmState>> = RefCell::new(HashMap::new());
+    static DIGITAL_PWM_RATE: RefCell<f64> = const { RefCell::new(0.0) };
+}
+
+pub fn dio(handle: HAL_DigitalHandle) -> DioState {
+    DIOS.with(|dios| dios.borrow()[&handle].clone())
+}
+
+pub fn digital_pwm(handle: HAL_DigitalPWMHandle) -> DigitalPwmState {
+    DIGITAL_PWMS.with(|generators| generators.borrow()[&handle].clone())
+}
+
+pub fn digital_pwm_rate() -> f64 {
+    DIGITAL_PWM_RATE.with(|rate| *rate.borrow())
+}
+
+fn with_dio<T>(handle: HAL_DigitalHandle, status: *mut i32, f: impl FnOnce(&mut DioState) -> T) -> T
+where
+    T: Default,
+{
+    DIOS.with(|dios| match dios.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+fn with_digital_pwm<T>(handle: HAL_DigitalPWMHandle, status: *mut i32, f: impl FnOnce(&mut DigitalPwmState) -> T) -> T
+where
+    T: Default,
+{
+    DIGITAL_PWMS.with(|generators| match generators.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetNumDigitalChannels() -> i32 {
+    NUM_DIGITAL_CHANNELS
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckDIOChannel(channel: i32) -> HAL_Bool {
+    (0..NUM_DIGITAL_CHANNELS).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeDIOPort(port: HAL_PortHandle, input: HAL_Bool, status: *mut i32) -> HAL_DigitalHandle {
+    if HAL_CheckDIOChannel(port) == 0 {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    let handle = port + 1;
+
+    DIOS.with(|dios| {
+        let mut dios = dios.borrow_mut();
+
+        if let Some(DioState { freed: false, .. }) = dios.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        let state = DioState {
+            input: input != 0,
+            ..DioState::default()
+        };
+
+        dios.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeDIOPort(handle: HAL_DigitalHandle) {
+    with_dio(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetDIO(handle: HAL_DigitalHandle, value: HAL_Bool, status: *mut i32) {
+    with_dio(handle, status, |state| {
+        if state.input {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        } else {
+            state.value = value != 0;
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetDIO(handle: HAL_DigitalHandle, status: *mut i32) -> HAL_Bool {
+    with_dio(handle, status, |state| state.value as HAL_Bool)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_Pulse(handle: HAL_DigitalHandle, length: f64, status: *mut i32) {
+    with_dio(handle, status, |state| state.pulse_length = Some(length))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_IsPulsing(handle: HAL_DigitalHandle, status: *mut i32) -> HAL_Bool {
+    // Time doesn't pass in the stub, so a pulse never finishes.
+    with_dio(handle, status, |state| state.pulse_length.is_some() as HAL_Bool)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetFilterSelect(handle: HAL_DigitalHandle, index: i32, status: *mut i32) {
+    with_dio(handle, status, |state| state.filter_index = index)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetDigitalPWMRate(rate: f64, _status: *mut i32) {
+    DIGITAL_PWM_RATE.with(|current| *current.borrow_mut() = rate)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_AllocateDigitalPWM(status: *mut i32) -> HAL_DigitalPWMHandle {
+    DIGITAL_PWMS.with(|generators| {
+        let mut generators = generators.borrow_mut();
+
+        if generators.values().filter(|state| !state.freed).count() == NUM_DIGITAL_PWM_OUTPUTS {
+            unsafe { *status = NO_AVAILABLE_RESOURCES };
+            return HAL_kInvalidHandle;
+        }
+
+        let handle = generators.keys().max().map_or(1, |handle| handle + 1);
+
+        generators.insert(handle, DigitalPwmState::default());
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeDigitalPWM(handle: HAL_DigitalPWMHandle, status: *mut i32) {
+    with_digital_pwm(handle, status, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetDigitalPWMDutyCycle(handle: HAL_DigitalPWMHandle, duty_cycle: f64, status: *mut i32) {
+    with_digital_pwm(handle, status, |state| state.duty_cycle = duty_cycle)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetDigitalPWMOutputChannel(handle: HAL_DigitalPWMHandle, channel: i32, status: *mut i32) {
+    // Routing to one past the last channel is how the FPGA disconnects a generator.
+    with_digital_pwm(handle, status, |state| {
+        state.output_channel = if channel < NUM_DIGITAL_CHANNELS { Some(channel) } else { None };
+    })
+}
+
+/// The roboRIO's 40 MHz FPGA clock.
+pub const SYSTEM_CLOCK_TICKS_PER_MICROSECOND: i32 = 40;
+
+thread_local! {
+    static FILTER_PERIODS: RefCell<[i64; 3]> = const { RefCell::new([0; 3]) };
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetSystemClockTicksPerMicrosecond() -> i32 {
+    SYSTEM_CLOCK_TICKS_PER_MICROSECOND
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetFilterPeriod(index: i32, value: i64, status: *mut i32) {
+    FILTER_PERIODS.with(|periods| match periods.borrow_mut().get_mut(index as usize) {
+        Some(period) => *period = value,
+        None => unsafe { *status = PARAMETER_OUT_OF_RANGE },
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetFilterPeriod(index: i32, status: *mut i32) -> i64 {
+    FILTER_PERIODS.with(|periods| match periods.borrow().get(index as usize) {
+        Some(&period) => period,
+        None => {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+            0
+        }
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EncoderState {
+    pub source_a: HAL_Handle,
+    pub source_b: HAL_Handle,
+    pub reverse_direction: bool,
+    pub encoding: HAL_EncoderEncodingType::Type,
+    pub index_source: Option<(HAL_Handle, HAL_EncoderIndexingType::Type)>,
+    pub distance_per_pulse: f64,
+    pub samples_to_average: i32,
+    pub freed: bool,
+}
+
+thread_local! {
+    static ENCODERS: RefCell<HashMap<HAL_EncoderHandle, EncoderState>> = RefCell::new(HashMap::new());
+}
+
+pub fn encoder(handle: HAL_EncoderHandle) -> EncoderState {
+    ENCODERS.with(|encoders| encoders.borrow()[&handle].clone())
+}
+
+fn with_encoder<T>(handle: HAL_EncoderHandle, status: *mut i32, f: impl FnOnce(&mut EncoderState) -> T) -> T
+where
+    T: Default,
+{
+    ENCODERS.with(|encoders| match encoders.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeEncoder(
+    source_a: HAL_Handle,
+    _trigger_type_a: HAL_AnalogTriggerType::Type,
+    source_b: HAL_Handle,
+    _trigger_type_b: HAL_AnalogTriggerType::Type,
+    reverse_direction: HAL_Bool,
+    encoding: HAL_EncoderEncodingType::Type,
+    _status: *mut i32,
+) -> HAL_EncoderHandle {
+    ENCODERS.with(|encoders| {
+        let mut encoders = encoders.borrow_mut();
+        let handle = encoders.keys().max().map_or(1, |handle| handle + 1);
+        let state = EncoderState {
+            source_a,
+            source_b,
+            reverse_direction: reverse_direction != 0,
+            encoding,
+            distance_per_pulse: 1.0,
+            samples_to_average: 1,
+            ..EncoderState::default()
+        };
+
+        encoders.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeEncoder(handle: HAL_EncoderHandle, status: *mut i32) {
+    with_encoder(handle, status, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetEncoderEncodingScale(handle: HAL_EncoderHandle, status: *mut i32) -> i32 {
+    with_encoder(handle, status, |state| match state.encoding {
+        HAL_EncoderEncodingType::HAL_Encoder_k1X => 1,
+        HAL_EncoderEncodingType::HAL_Encoder_k2X => 2,
+        _ => 4,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetEncoderDistancePerPulse(handle: HAL_EncoderHandle, distance_per_pulse: f64, status: *mut i32) {
+    with_encoder(handle, status, |state| {
+        if distance_per_pulse == 0.0 {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        } else {
+            state.distance_per_pulse = distance_per_pulse;
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetEncoderDistancePerPulse(handle: HAL_EncoderHandle, status: *mut i32) -> f64 {
+    with_encoder(handle, status, |state| state.distance_per_pulse)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetEncoderReverseDirection(
+    handle: HAL_EncoderHandle,
+    reverse_direction: HAL_Bool,
+    status: *mut i32,
+) {
+    with_encoder(handle, status, |state| state.reverse_direction = reverse_direction != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetEncoderSamplesToAverage(handle: HAL_EncoderHandle, samples: i32, status: *mut i32) {
+    with_encoder(handle, status, |state| {
+        if (1..=127).contains(&samples) {
+            state.samples_to_average = samples;
+        } else {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetEncoderSamplesToAverage(handle: HAL_EncoderHandle, status: *mut i32) -> i32 {
+    with_encoder(handle, status, |state| state.samples_to_average)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetEncoderIndexSource(
+    handle: HAL_EncoderHandle,
+    source: HAL_Handle,
+    _trigger_type: HAL_AnalogTriggerType::Type,
+    indexing: HAL_EncoderIndexingType::Type,
+    status: *mut i32,
+) {
+    with_encoder(handle, status, |state| state.index_source = Some((source, indexing)))
+}
+
+pub const NUM_ANALOG_INPUTS: i32 = 8;
+pub const NUM_ACCUMULATORS: i32 = 2;
+
+#[derive(Clone, Debug, Default)]
+pub struct AccumulatorState {
+    pub value: i64,
+    pub count: i64,
+    pub center: i32,
+    pub deadband: i32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnalogInputState {
+    pub channel: i32,
+    pub voltage: f64,
+    pub average_bits: i32,
+    pub oversample_bits: i32,
+    pub accumulator: Option<AccumulatorState>,
+    pub freed: bool,
+}
+
+thread_local! {
+    static ANALOG_INPUTS: RefCell<HashMap<HAL_AnalogInputHandle, AnalogInputState>> = RefCell::new(HashMap::new());
+    static ANALOG_SAMPLE_RATE: RefCell<f64> = const { RefCell::new(50_000.0) };
+}
+
+pub fn analog_input(handle: HAL_AnalogInputHandle) -> AnalogInputState {
+    ANALOG_INPUTS.with(|inputs| inputs.borrow()[&handle].clone())
+}
+
+pub fn set_analog_voltage(handle: HAL_AnalogInputHandle, voltage: f64) {
+    with_analog_input(handle, &mut 0, |state| state.voltage = voltage)
+}
+
+/// Loads the accumulator as if the FPGA had summed `count` samples to `value`.
+pub fn set_accumulator(handle: HAL_AnalogInputHandle, value: i64, count: i64) {
+    with_accumulator(handle, &mut 0, |accumulator| {
+        accumulator.value = value;
+        accumulator.count = count;
+    })
+}
+
+fn with_analog_input<T>(
+    handle: HAL_AnalogInputHandle,
+    status: *mut i32,
+    f: impl FnOnce(&mut AnalogInputState) -> T,
+) -> T
+where
+    T: Default,
+{
+    ANALOG_INPUTS.with(|inputs| match inputs.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+fn with_accumulator<T>(handle: HAL_AnalogInputHandle, status: *mut i32, f: impl FnOnce(&mut AccumulatorState) -> T) -> T
+where
+    T: Default,
+{
+    with_analog_input(handle, status, |state| match state.accumulator.as_mut() {
+        Some(accumulator) => f(accumulator),
+        None => {
+            unsafe { *status = HAL_INVALID_ACCUMULATOR_CHANNEL };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckAnalogInputChannel(channel: i32) -> HAL_Bool {
+    (0..NUM_ANALOG_INPUTS).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeAnalogInputPort(port: HAL_PortHandle, status: *mut i32) -> HAL_AnalogInputHandle {
+    if HAL_CheckAnalogInputChannel(port) == 0 {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    let handle = port + 1;
+
+    ANALOG_INPUTS.with(|inputs| {
+        let mut inputs = inputs.borrow_mut();
+
+        if let Some(AnalogInputState { freed: false, .. }) = inputs.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        let state = AnalogInputState {
+            channel: port,
+            ..AnalogInputState::default()
+        };
+
+        inputs.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeAnalogInputPort(handle: HAL_AnalogInputHandle) {
+    with_analog_input(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogVoltage(handle: HAL_AnalogInputHandle, status: *mut i32) -> f64 {
+    with_analog_input(handle, status, |state| state.voltage)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogAverageBits(handle: HAL_AnalogInputHandle, bits: i32, status: *mut i32) {
+    with_analog_input(handle, status, |state| state.average_bits = bits)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogAverageBits(handle: HAL_AnalogInputHandle, status: *mut i32) -> i32 {
+    with_analog_input(handle, status, |state| state.average_bits)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogOversampleBits(handle: HAL_AnalogInputHandle, bits: i32, status: *mut i32) {
+    with_analog_input(handle, status, |state| state.oversample_bits = bits)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogOversampleBits(handle: HAL_AnalogInputHandle, status: *mut i32) -> i32 {
+    with_analog_input(handle, status, |state| state.oversample_bits)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogSampleRate(samples_per_second: f64, _status: *mut i32) {
+    ANALOG_SAMPLE_RATE.with(|rate| *rate.borrow_mut() = samples_per_second)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogSampleRate(_status: *mut i32) -> f64 {
+    ANALOG_SAMPLE_RATE.with(|rate| *rate.borrow())
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_IsAccumulatorChannel(handle: HAL_AnalogInputHandle, status: *mut i32) -> HAL_Bool {
+    with_analog_input(handle, status, |state| (state.channel < NUM_ACCUMULATORS) as HAL_Bool)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitAccumulator(handle: HAL_AnalogInputHandle, status: *mut i32) {
+    with_analog_input(handle, status, |state| {
+        if state.channel < NUM_ACCUMULATORS {
+            state.accumulator = Some(AccumulatorState::default());
+        } else {
+            unsafe { *status = HAL_INVALID_ACCUMULATOR_CHANNEL };
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ResetAccumulator(handle: HAL_AnalogInputHandle, status: *mut i32) {
+    with_accumulator(handle, status, |accumulator| {
+        accumulator.value = 0;
+        accumulator.count = 0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAccumulatorCenter(handle: HAL_AnalogInputHandle, center: i32, status: *mut i32) {
+    with_accumulator(handle, status, |accumulator| accumulator.center = center)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAccumulatorDeadband(handle: HAL_AnalogInputHandle, deadband: i32, status: *mut i32) {
+    with_accumulator(handle, status, |accumulator| accumulator.deadband = deadband)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccumulatorValue(handle: HAL_AnalogInputHandle, status: *mut i32) -> i64 {
+    with_accumulator(handle, status, |accumulator| accumulator.value)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccumulatorCount(handle: HAL_AnalogInputHandle, status: *mut i32) -> i64 {
+    with_accumulator(handle, status, |accumulator| accumulator.count)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccumulatorOutput(
+    handle: HAL_AnalogInputHandle,
+    value: *mut i64,
+    count: *mut i64,
+    status: *mut i32,
+) {
+    with_accumulator(handle, status, |accumulator| unsafe {
+        *value = accumulator.value;
+        *count = accumulator.count;
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnalogGyroState {
+    pub input: HAL_AnalogInputHandle,
+    pub volts_per_degree_per_second: f64,
+    pub offset: f64,
+    pub center: i32,
+    pub deadband: f64,
+    pub angle: f64,
+    pub calibrations: u32,
+    pub resets: u32,
+    pub freed: bool,
+}
+
+thread_local! {
+    static ANALOG_GYROS: RefCell<HashMap<HAL_GyroHandle, AnalogGyroState>> = RefCell::new(HashMap::new());
+}
+
+pub fn analog_gyro(handle: HAL_GyroHandle) -> AnalogGyroState {
+    ANALOG_GYROS.with(|gyros| gyros.borrow()[&handle].clone())
+}
+
+pub fn set_analog_gyro_angle(handle: HAL_GyroHandle, angle: f64) {
+    with_analog_gyro(handle, &mut 0, |state| state.angle = angle)
+}
+
+fn with_analog_gyro<T>(handle: HAL_GyroHandle, status: *mut i32, f: impl FnOnce(&mut AnalogGyroState) -> T) -> T
+where
+    T: Default,
+{
+    ANALOG_GYROS.with(|gyros| match gyros.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeAnalogGyro(input: HAL_AnalogInputHandle, status: *mut i32) -> HAL_GyroHandle {
+    if HAL_IsAccumulatorChannel(input, status) == 0 {
+        unsafe { *status = HAL_INVALID_ACCUMULATOR_CHANNEL };
+        return HAL_kInvalidHandle;
+    }
+
+    ANALOG_GYROS.with(|gyros| {
+        let mut gyros = gyros.borrow_mut();
+        let handle = gyros.keys().max().map_or(1, |handle| handle + 1);
+        let state = AnalogGyroState {
+            input,
+            ..AnalogGyroState::default()
+        };
+
+        gyros.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetupAnalogGyro(handle: HAL_GyroHandle, status: *mut i32) {
+    with_analog_gyro(handle, status, |state| state.volts_per_degree_per_second = 0.007)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeAnalogGyro(handle: HAL_GyroHandle) {
+    with_analog_gyro(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogGyroParameters(
+    handle: HAL_GyroHandle,
+    volts_per_degree_per_second: f64,
+    offset: f64,
+    center: i32,
+    status: *mut i32,
+) {
+    with_analog_gyro(handle, status, |state| {
+        state.volts_per_degree_per_second = volts_per_degree_per_second;
+        state.offset = offset;
+        state.center = center;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogGyroVoltsPerDegreePerSecond(handle: HAL_GyroHandle, volts: f64, status: *mut i32) {
+    with_analog_gyro(handle, status, |state| state.volts_per_degree_per_second = volts)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogGyroDeadband(handle: HAL_GyroHandle, volts: f64, status: *mut i32) {
+    with_analog_gyro(handle, status, |state| state.deadband = volts)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogGyroOffset(handle: HAL_GyroHandle, status: *mut i32) -> f64 {
+    with_analog_gyro(handle, status, |state| state.offset)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogGyroCenter(handle: HAL_GyroHandle, status: *mut i32) -> i32 {
+    with_analog_gyro(handle, status, |state| state.center)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CalibrateAnalogGyro(handle: HAL_GyroHandle, status: *mut i32) {
+    with_analog_gyro(handle, status, |state| {
+        state.calibrations += 1;
+        state.angle = 0.0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ResetAnalogGyro(handle: HAL_GyroHandle, status: *mut i32) {
+    with_analog_gyro(handle, status, |state| {
+        state.resets += 1;
+        state.angle = 0.0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogGyroAngle(handle: HAL_GyroHandle, status: *mut i32) -> f64 {
+    with_analog_gyro(handle, status, |state| state.angle)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogGyroRate(handle: HAL_GyroHandle, status: *mut i32) -> f64 {
+    with_analog_gyro(handle, status, |_| 0.0)
+}
+
+pub const NUM_SOLENOID_MODULES: i32 = 63;
+pub const NUM_SOLENOID_CHANNELS: i32 = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct SolenoidState {
+    pub module: i32,
+    pub channel: i32,
+    pub on: bool,
+    pub freed: bool,
+}
+
+thread_local! {
+    static SOLENOIDS: RefCell<HashMap<HAL_SolenoidHandle, SolenoidState>> = RefCell::new(HashMap::new());
+    static SOLENOID_WRITES: RefCell<Vec<(i32, bool)>> = const { RefCell::new(Vec::new()) };
+}
+
+pub fn solenoid(handle: HAL_SolenoidHandle) -> SolenoidState {
+    SOLENOIDS.with(|solenoids| solenoids.borrow()[&handle].clone())
+}
+
+/// Drains every `(channel, on)` written to a solenoid so far, oldest first.
+pub fn take_solenoid_writes() -> Vec<(i32, bool)> {
+    SOLENOID_WRITES.with(|writes| writes.borrow_mut().split_off(0))
+}
+
+fn with_solenoid<T>(handle: HAL_SolenoidHandle, status: *mut i32, f: impl FnOnce(&mut SolenoidState) -> T) -> T
+where
+    T: Default,
+{
+    SOLENOIDS.with(|solenoids| match solenoids.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPortWithModule(module: i32, channel: i32) -> HAL_PortHandle {
+    module << 8 | channel
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckSolenoidModule(module: i32) -> HAL_Bool {
+    (0..NUM_SOLENOID_MODULES).contains(&module) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckSolenoidChannel(channel: i32) -> HAL_Bool {
+    (0..NUM_SOLENOID_CHANNELS).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeSolenoidPort(port: HAL_PortHandle, status: *mut i32) -> HAL_SolenoidHandle {
+    let handle = port + 1;
+
+    SOLENOIDS.with(|solenoids| {
+        let mut solenoids = solenoids.borrow_mut();
+
+        if let Some(SolenoidState { freed: false, .. }) = solenoids.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        let state = SolenoidState {
+            module: port >> 8,
+            channel: port & 0xff,
+            ..SolenoidState::default()
+        };
+
+        solenoids.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeSolenoidPort(handle: HAL_SolenoidHandle) {
+    with_solenoid(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSolenoid(handle: HAL_SolenoidHandle, value: HAL_Bool, status: *mut i32) {
+    with_solenoid(handle, status, |state| {
+        state.on = value != 0;
+        SOLENOID_WRITES.with(|writes| writes.borrow_mut().push((state.channel, state.on)));
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetSolenoid(handle: HAL_SolenoidHandle, status: *mut i32) -> HAL_Bool {
+    with_solenoid(handle, status, |state| state.on as HAL_Bool)
+}
+
+pub const NUM_PDP_MODULES: i32 = 63;
+
+#[derive(Clone, Debug, Default)]
+pub struct PdpState {
+    pub currents: [f64; 16],
+    pub energy: f64,
+    pub cleaned: bool,
+}
+
+thread_local! {
+    static PDPS: RefCell<HashMap<HAL_PDPHandle, PdpState>> = RefCell::new(HashMap::new());
+}
+
+pub fn pdp(handle: HAL_PDPHandle) -> PdpState {
+    PDPS.with(|pdps| pdps.borrow()[&handle].clone())
+}
+
+pub fn set_pdp(handle: HAL_PDPHandle, currents: [f64; 16], energy: f64) {
+    with_pdp(handle, &mut 0, |state| {
+        state.currents = currents;
+        state.energy = energy;
+    })
+}
+
+fn with_pdp<T>(handle: HAL_PDPHandle, status: *mut i32, f: impl FnOnce(&mut PdpState) -> T) -> T
+where
+    T: Default,
+{
+    PDPS.with(|pdps| match pdps.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.cleaned => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckPDPModule(module: i32) -> HAL_Bool {
+    (0..NUM_PDP_MODULES).contains(&module) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckPDPChannel(channel: i32) -> HAL_Bool {
+    (0..16).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializePDP(module: i32, status: *mut i32) -> HAL_PDPHandle {
+    if HAL_CheckPDPModule(module) == 0 {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    let handle = module + 1;
+
+    PDPS.with(|pdps| pdps.borrow_mut().insert(handle, PdpState::default()));
+    handle
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CleanPDP(handle: HAL_PDPHandle) {
+    with_pdp(handle, &mut 0, |state| state.cleaned = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPDPChannelCurrent(handle: HAL_PDPHandle, channel: i32, status: *mut i32) -> f64 {
+    with_pdp(handle, status, |state| state.currents[channel as usize])
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPDPAllChannelCurrents(handle: HAL_PDPHandle, currents: *mut f64, status: *mut i32) {
+    with_pdp(handle, status, |state| unsafe {
+        std::ptr::copy_nonoverlapping(state.currents.as_ptr(), currents, state.currents.len());
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetPDPTotalEnergy(handle: HAL_PDPHandle, status: *mut i32) -> f64 {
+    with_pdp(handle, status, |state| state.energy)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ResetPDPTotalEnergy(handle: HAL_PDPHandle, status: *mut i32) {
+    with_pdp(handle, status, |state| state.energy = 0.0)
+}
+
+pub const NUM_RELAY_CHANNELS: i32 = 4;
+
+#[derive(Clone, Debug, Default)]
+pub struct RelayState {
+    pub channel: i32,
+    pub forward: bool,
+    pub on: bool,
+    pub freed: bool,
+}
+
+thread_local! {
+    static RELAYS: RefCell<HashMap<HAL_RelayHandle, RelayState>> = RefCell::new(HashMap::new());
+}
+
+pub fn relay(handle: HAL_RelayHandle) -> RelayState {
+    RELAYS.with(|relays| relays.borrow()[&handle].clone())
+}
+
+fn with_relay<T>(handle: HAL_RelayHandle, status: *mut i32, f: impl FnOnce(&mut RelayState) -> T) -> T
+where
+    T: Default,
+{
+    RELAYS.with(|relays| match relays.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckRelayChannel(channel: i32) -> HAL_Bool {
+    (0..NUM_RELAY_CHANNELS).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeRelayPort(
+    port: HAL_PortHandle,
+    forward: HAL_Bool,
+    status: *mut i32,
+) -> HAL_RelayHandle {
+    if HAL_CheckRelayChannel(port) == 0 {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    // The forward and reverse outputs of a channel are separate resources.
+    let handle = port * 2 + forward + 1;
+
+    RELAYS.with(|relays| {
+        let mut relays = relays.borrow_mut();
+
+        if let Some(RelayState { freed: false, .. }) = relays.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        let state = RelayState {
+            channel: port,
+            forward: forward != 0,
+            ..RelayState::default()
+        };
+
+        relays.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeRelayPort(handle: HAL_RelayHandle) {
+    with_relay(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetRelay(handle: HAL_RelayHandle, on: HAL_Bool, status: *mut i32) {
+    with_relay(handle, status, |state| state.on = on != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetRelay(handle: HAL_RelayHandle, status: *mut i32) -> HAL_Bool {
+    with_relay(handle, status, |state| state.on as HAL_Bool)
+}
+
+pub const NUM_COUNTERS: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct CounterState {
+    pub index: i32,
+    pub mode: HAL_Counter_Mode::Type,
+    pub up_source: Option<(HAL_Handle, bool, bool)>,
+    pub down_source: Option<(HAL_Handle, bool, bool)>,
+    pub semi_period_high: bool,
+    pub pulse_length_threshold: f64,
+    pub max_period: f64,
+    pub update_when_empty: bool,
+    pub samples_to_average: i32,
+    pub reverse_direction: bool,
+    pub count: i32,
+    pub period: f64,
+    pub freed: bool,
+}
+
+thread_local! {
+    static COUNTERS: RefCell<HashMap<HAL_CounterHandle, CounterState>> = RefCell::new(HashMap::new());
+}
+
+pub fn counter(handle: HAL_CounterHandle) -> CounterState {
+    COUNTERS.with(|counters| counters.borrow()[&handle].clone())
+}
+
+pub fn live_counters() -> usize {
+    COUNTERS.with(|counters| counters.borrow().values().filter(|state| !state.freed).count())
+}
+
+/// Sets what the FPGA has counted and the period it measured between the last two counts.
+pub fn set_counter(handle: HAL_CounterHandle, count: i32, period: f64) {
+    with_counter(handle, &mut 0, |state| {
+        state.count = count;
+        state.period = period;
+    })
+}
+
+fn with_counter<T>(handle: HAL_CounterHandle, status: *mut i32, f: impl FnOnce(&mut CounterState) -> T) -> T
+where
+    T: Default,
+{
+    COUNTERS.with(|counters| match counters.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeCounter(
+    mode: HAL_Counter_Mode::Type,
+    index: *mut i32,
+    status: *mut i32,
+) -> HAL_CounterHandle {
+    COUNTERS.with(|counters| {
+        let mut counters = counters.borrow_mut();
+        let live = |index: i32| counters.values().any(|state| !state.freed && state.index == index);
+
+        let free = match (0..NUM_COUNTERS as i32).find(|&index| !live(index)) {
+            Some(free) => free,
+            None => {
+                unsafe { *status = NO_AVAILABLE_RESOURCES };
+                return HAL_kInvalidHandle;
+            }
+        };
+
+        let handle = counters.keys().max().map_or(1, |handle| handle + 1);
+        let state = CounterState {
+            index: free,
+            mode,
+            ..CounterState::default()
+        };
+
+        counters.insert(handle, state);
+        unsafe { *index = free };
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeCounter(handle: HAL_CounterHandle, status: *mut i32) {
+    with_counter(handle, status, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterUpSource(
+    handle: HAL_CounterHandle,
+    source: HAL_Handle,
+    _trigger_type: HAL_AnalogTriggerType::Type,
+    status: *mut i32,
+) {
+    with_counter(handle, status, |state| state.up_source = Some((source, false, false)))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterUpSourceEdge(
+    handle: HAL_CounterHandle,
+    rising: HAL_Bool,
+    falling: HAL_Bool,
+    status: *mut i32,
+) {
+    with_counter(handle, status, |state| match state.up_source.as_mut() {
+        Some(source) => *source = (source.0, rising != 0, falling != 0),
+        None => unsafe { *status = PARAMETER_OUT_OF_RANGE },
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterDownSource(
+    handle: HAL_CounterHandle,
+    source: HAL_Handle,
+    _trigger_type: HAL_AnalogTriggerType::Type,
+    status: *mut i32,
+) {
+    with_counter(handle, status, |state| state.down_source = Some((source, false, false)))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterDownSourceEdge(
+    handle: HAL_CounterHandle,
+    rising: HAL_Bool,
+    falling: HAL_Bool,
+    status: *mut i32,
+) {
+    with_counter(handle, status, |state| match state.down_source.as_mut() {
+        Some(source) => *source = (source.0, rising != 0, falling != 0),
+        None => unsafe { *status = PARAMETER_OUT_OF_RANGE },
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterUpDownMode(handle: HAL_CounterHandle, status: *mut i32) {
+    with_counter(handle, status, |state| state.mode = HAL_Counter_Mode::HAL_Counter_kTwoPulse)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterExternalDirectionMode(handle: HAL_CounterHandle, status: *mut i32) {
+    with_counter(handle, status, |state| state.mode = HAL_Counter_Mode::HAL_Counter_kExternalDirection)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterSemiPeriodMode(handle: HAL_CounterHandle, high: HAL_Bool, status: *mut i32) {
+    with_counter(handle, status, |state| {
+        state.mode = HAL_Counter_Mode::HAL_Counter_kSemiperiod;
+        state.semi_period_high = high != 0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterPulseLengthMode(handle: HAL_CounterHandle, threshold: f64, status: *mut i32) {
+    with_counter(handle, status, |state| {
+        state.mode = HAL_Counter_Mode::HAL_Counter_kPulseLength;
+        state.pulse_length_threshold = threshold;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterMaxPeriod(handle: HAL_CounterHandle, max_period: f64, status: *mut i32) {
+    with_counter(handle, status, |state| state.max_period = max_period)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterUpdateWhenEmpty(handle: HAL_CounterHandle, enabled: HAL_Bool, status: *mut i32) {
+    with_counter(handle, status, |state| state.update_when_empty = enabled != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterSamplesToAverage(handle: HAL_CounterHandle, samples: i32, status: *mut i32) {
+    with_counter(handle, status, |state| {
+        if (1..=127).contains(&samples) {
+            state.samples_to_average = samples;
+        } else {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCounterReverseDirection(
+    handle: HAL_CounterHandle,
+    reverse_direction: HAL_Bool,
+    status: *mut i32,
+) {
+    with_counter(handle, status, |state| state.reverse_direction = reverse_direction != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetCounter(handle: HAL_CounterHandle, status: *mut i32) -> i32 {
+    with_counter(handle, status, |state| state.count)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetCounterPeriod(handle: HAL_CounterHandle, status: *mut i32) -> f64 {
+    with_counter(handle, status, |state| state.period)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnalogTriggerState {
+    pub input: HAL_AnalogInputHandle,
+    pub index: i32,
+    pub lower: f64,
+    pub upper: f64,
+    pub averaged: bool,
+    pub filtered: bool,
+    pub state: bool,
+    pub cleaned: bool,
+}
+
+thread_local! {
+    static ANALOG_TRIGGERS: RefCell<HashMap<HAL_AnalogTriggerHandle, AnalogTriggerState>> =
+        RefCell::new(HashMap::new());
+}
+
+pub fn analog_trigger(handle: HAL_AnalogTriggerHandle) -> AnalogTriggerState {
+    ANALOG_TRIGGERS.with(|triggers| triggers.borrow()[&handle].clone())
+}
+
+fn with_analog_trigger<T>(
+    handle: HAL_AnalogTriggerHandle,
+    status: *mut i32,
+    f: impl FnOnce(&mut AnalogTriggerState) -> T,
+) -> T
+where
+    T: Default,
+{
+    ANALOG_TRIGGERS.with(|triggers| match triggers.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.cleaned => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+/// Compares the input's voltage against the limits, with the state output latching high above
+/// the upper limit until the voltage drops below the lower one.
+fn update_analog_trigger(state: &mut AnalogTriggerState, status: *mut i32) -> bool {
+    let voltage = HAL_GetAnalogVoltage(state.input, status);
+
+    if voltage > state.upper {
+        state.state = true;
+    } else if voltage < state.lower {
+        state.state = false;
+    }
+
+    (state.lower..=state.upper).contains(&voltage)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeAnalogTrigger(
+    input: HAL_AnalogInputHandle,
+    status: *mut i32,
+) -> HAL_AnalogTriggerHandle {
+    with_analog_input(input, status, |_| ());
+
+    ANALOG_TRIGGERS.with(|triggers| {
+        let mut triggers = triggers.borrow_mut();
+        let handle = triggers.keys().max().map_or(1, |handle| handle + 1);
+        let state = AnalogTriggerState {
+            input,
+            index: handle - 1,
+            ..AnalogTriggerState::default()
+        };
+
+        triggers.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CleanAnalogTrigger(handle: HAL_AnalogTriggerHandle, status: *mut i32) {
+    with_analog_trigger(handle, status, |state| state.cleaned = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogTriggerFPGAIndex(handle: HAL_AnalogTriggerHandle, status: *mut i32) -> i32 {
+    with_analog_trigger(handle, status, |state| state.index)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogTriggerLimitsVoltage(
+    handle: HAL_AnalogTriggerHandle,
+    lower: f64,
+    upper: f64,
+    status: *mut i32,
+) {
+    with_analog_trigger(handle, status, |state| {
+        if lower > upper {
+            unsafe { *status = ANALOG_TRIGGER_LIMIT_ORDER_ERROR };
+        } else {
+            state.lower = lower;
+            state.upper = upper;
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogTriggerAveraged(handle: HAL_AnalogTriggerHandle, averaged: HAL_Bool, status: *mut i32) {
+    with_analog_trigger(handle, status, |state| {
+        if state.filtered {
+            unsafe { *status = INCOMPATIBLE_STATE };
+        } else {
+            state.averaged = averaged != 0;
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogTriggerFiltered(handle: HAL_AnalogTriggerHandle, filtered: HAL_Bool, status: *mut i32) {
+    with_analog_trigger(handle, status, |state| {
+        if state.averaged {
+            unsafe { *status = INCOMPATIBLE_STATE };
+        } else {
+            state.filtered = filtered != 0;
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogTriggerInWindow(handle: HAL_AnalogTriggerHandle, status: *mut i32) -> HAL_Bool {
+    with_analog_trigger(handle, status, |state| update_analog_trigger(state, status) as HAL_Bool)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogTriggerTriggerState(handle: HAL_AnalogTriggerHandle, status: *mut i32) -> HAL_Bool {
+    with_analog_trigger(handle, status, |state| {
+        update_analog_trigger(state, status);
+        state.state as HAL_Bool
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogTriggerOutput(
+    handle: HAL_AnalogTriggerHandle,
+    trigger_type: HAL_AnalogTriggerType::Type,
+    status: *mut i32,
+) -> HAL_Bool {
+    with_analog_trigger(handle, status, |state| {
+        let in_window = update_analog_trigger(state, status);
+
+        match trigger_type {
+            HAL_AnalogTriggerType::HAL_Trigger_kInWindow => in_window as HAL_Bool,
+            HAL_AnalogTriggerType::HAL_Trigger_kState => state.state as HAL_Bool,
+            _ => {
+                unsafe { *status = ANALOG_TRIGGER_PULSE_OUTPUT_ERROR };
+                0
+            }
+        }
+    })
+}
+
+/// A register-mapped device on a stubbed I2C bus that auto-increments its register pointer.
+#[derive(Clone, Debug)]
+pub struct I2cDevice {
+    pub registers: [u8; 256],
+    pub pointer: u8,
+}
+
+thread_local! {
+    static I2C_OPEN: RefCell<[u32; 2]> = const { RefCell::new([0; 2]) };
+    static I2C_DEVICES: RefCell<HashMap<(HAL_I2CPort::Type, i32), I2cDevice>> = RefCell::new(HashMap::new());
+}
+
+pub fn i2c_open_count(port: HAL_I2CPort::Type) -> u32 {
+    I2C_OPEN.with(|open| open.borrow()[port as usize])
+}
+
+pub fn add_i2c_device(port: HAL_I2CPort::Type, address: i32, registers: &[(u8, u8)]) {
+    let mut device = I2cDevice {
+        registers: [0; 256],
+        pointer: 0,
+    };
+
+    for &(register, value) in registers {
+        device.registers[register as usize] = value;
+    }
+
+    I2C_DEVICES.with(|devices| devices.borrow_mut().insert((port, address), device));
+}
+
+pub fn i2c_register(port: HAL_I2CPort::Type, address: i32, register: u8) -> u8 {
+    I2C_DEVICES.with(|devices| devices.borrow()[&(port, address)].registers[register as usize])
+}
+
+/// Runs a write then a read against a device, returning -1 when nothing acknowledges the address.
+fn i2c_transfer(port: HAL_I2CPort::Type, address: i32, send: &[u8], receive: &mut [u8]) -> i32 {
+    I2C_DEVICES.with(|devices| match devices.borrow_mut().get_mut(&(port, address)) {
+        Some(device) => {
+            if let Some((&register, data)) = send.split_first() {
+                device.pointer = register;
+
+                for &value in data {
+                    device.registers[device.pointer as usize] = value;
+                    device.pointer = device.pointer.wrapping_add(1);
+                }
+            }
+
+            for value in receive.iter_mut() {
+                *value = device.registers[device.pointer as usize];
+                device.pointer = device.pointer.wrapping_add(1);
+            }
+
+            0
+        }
+        None => -1,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeI2C(port: HAL_I2CPort::Type, status: *mut i32) {
+    I2C_OPEN.with(|open| match open.borrow_mut().get_mut(port as usize) {
+        Some(count) => *count += 1,
+        None => unsafe { *status = PARAMETER_OUT_OF_RANGE },
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CloseI2C(port: HAL_I2CPort::Type) {
+    I2C_OPEN.with(|open| open.borrow_mut()[port as usize] -= 1)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_TransactionI2C(
+    port: HAL_I2CPort::Type,
+    address: i32,
+    send: *const u8,
+    send_size: i32,
+    receive: *mut u8,
+    receive_size: i32,
+) -> i32 {
+    let send = unsafe { std::slice::from_raw_parts(send, send_size as usize) };
+    let receive = unsafe { std::slice::from_raw_parts_mut(receive, receive_size as usize) };
+
+    i2c_transfer(port, address, send, receive)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WriteI2C(port: HAL_I2CPort::Type, address: i32, send: *const u8, send_size: i32) -> i32 {
+    let send = unsafe { std::slice::from_raw_parts(send, send_size as usize) };
+
+    i2c_transfer(port, address, send, &mut [])
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadI2C(port: HAL_I2CPort::Type, address: i32, buffer: *mut u8, count: i32) -> i32 {
+    let receive = unsafe { std::slice::from_raw_parts_mut(buffer, count as usize) };
+
+    i2c_transfer(port, address, &[], receive)
+}
+
+/// What `HAL_ReadSPIAutoReceivedData` reports when the FPGA FIFO doesn't fill in time.
+pub const NIFPGA_FIFO_TIMEOUT: i32 = -50400;
+
+#[derive(Clone, Debug, Default)]
+pub struct SpiAutoState {
+    pub buffer_size: i32,
+    pub transmit_data: Vec<u8>,
+    pub zero_size: i32,
+    pub period: Option<f64>,
+    pub words: VecDeque<u32>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SpiState {
+    pub msb_first: bool,
+    pub sample_on_trailing: bool,
+    pub clock_idle_high: bool,
+    pub speed: i32,
+    pub auto: Option<SpiAutoState>,
+    pub closed: bool,
+}
+
+thread_local! {
+    static SPIS: RefCell<HashMap<HAL_SPIPort::Type, SpiState>> = RefCell::new(HashMap::new());
+}
+
+pub fn spi(port: HAL_SPIPort::Type) -> SpiState {
+    SPIS.with(|spis| spis.borrow()[&port].clone())
+}
+
+/// Queues one automatic transfer the way the FPGA stores it, a timestamp then a word per byte.
+pub fn push_spi_auto_transfer(port: HAL_SPIPort::Type, timestamp: u32, data: &[u8]) {
+    with_spi_auto(port, &mut 0, |auto| {
+        auto.words.push_back(timestamp);
+        auto.words.extend(data.iter().map(|&byte| u32::from(byte)));
+    })
+}
+
+fn with_spi<T>(port: HAL_SPIPort::Type, status: *mut i32, f: impl FnOnce(&mut SpiState) -> T) -> T
+where
+    T: Default,
+{
+    SPIS.with(|spis| match spis.borrow_mut().get_mut(&port) {
+        Some(state) if !state.closed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+fn with_spi_auto<T>(port: HAL_SPIPort::Type, status: *mut i32, f: impl FnOnce(&mut SpiAutoState) -> T) -> T
+where
+    T: Default,
+{
+    with_spi(port, status, |state| match state.auto.as_mut() {
+        Some(auto) => f(auto),
+        None => {
+            unsafe { *status = INCOMPATIBLE_STATE };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeSPI(port: HAL_SPIPort::Type, status: *mut i32) {
+    if port > HAL_SPIPort::HAL_SPI_kMXP {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return;
+    }
+
+    SPIS.with(|spis| spis.borrow_mut().insert(port, SpiState::default()));
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CloseSPI(port: HAL_SPIPort::Type) {
+    with_spi(port, &mut 0, |state| state.closed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSPIOpts(
+    port: HAL_SPIPort::Type,
+    msb_first: HAL_Bool,
+    sample_on_trailing: HAL_Bool,
+    clock_idle_high: HAL_Bool,
+) {
+    with_spi(port, &mut 0, |state| {
+        state.msb_first = msb_first != 0;
+        state.sample_on_trailing = sample_on_trailing != 0;
+        state.clock_idle_high = clock_idle_high != 0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSPISpeed(port: HAL_SPIPort::Type, speed: i32) {
+    with_spi(port, &mut 0, |state| state.speed = speed)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitSPIAuto(port: HAL_SPIPort::Type, buffer_size: i32, status: *mut i32) {
+    with_spi(port, status, |state| {
+        if state.auto.is_some() {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+        } else {
+            state.auto = Some(SpiAutoState {
+                buffer_size,
+                ..SpiAutoState::default()
+            });
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeSPIAuto(port: HAL_SPIPort::Type, status: *mut i32) {
+    with_spi(port, status, |state| state.auto = None)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSPIAutoTransmitData(
+    port: HAL_SPIPort::Type,
+    data: *const u8,
+    data_size: i32,
+    zero_size: i32,
+    status: *mut i32,
+) {
+    with_spi_auto(port, status, |auto| {
+        auto.transmit_data = unsafe { std::slice::from_raw_parts(data, data_size as usize) }.to_vec();
+        auto.zero_size = zero_size;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_StartSPIAutoRate(port: HAL_SPIPort::Type, period: f64, status: *mut i32) {
+    with_spi_auto(port, status, |auto| auto.period = Some(period))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_StopSPIAuto(port: HAL_SPIPort::Type, status: *mut i32) {
+    with_spi_auto(port, status, |auto| auto.period = None)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadSPIAutoReceivedData(
+    port: HAL_SPIPort::Type,
+    buffer: *mut u32,
+    count: i32,
+    _timeout: f64,
+    status: *mut i32,
+) -> i32 {
+    // Like the HAL, returns how many words are left rather than how many were read.
+    with_spi_auto(port, status, |auto| {
+        let count = count as usize;
+
+        if count > auto.words.len() {
+            unsafe { *status = NIFPGA_FIFO_TIMEOUT };
+        } else {
+            for index in 0..count {
+                unsafe { *buffer.add(index) = auto.words.pop_front().unwrap() };
+            }
+        }
+
+        auto.words.len() as i32
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AccelerometerState {
+    pub active: bool,
+    pub range: HAL_AccelerometerRange::Type,
+    /// Set if the range was written while the accelerometer was active, which the hardware ignores.
+    pub range_set_while_active: bool,
+    pub acceleration: (f64, f64, f64),
+}
+
+thread_local! {
+    static ACCELEROMETER: RefCell<AccelerometerState> = RefCell::new(AccelerometerState::default());
+}
+
+pub fn accelerometer() -> AccelerometerState {
+    ACCELEROMETER.with(|accelerometer| accelerometer.borrow().clone())
+}
+
+pub fn set_acceleration(x: f64, y: f64, z: f64) {
+    ACCELEROMETER.with(|accelerometer| accelerometer.borrow_mut().acceleration = (x, y, z))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAccelerometerActive(active: HAL_Bool) {
+    ACCELEROMETER.with(|accelerometer| accelerometer.borrow_mut().active = active != 0)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAccelerometerRange(range: HAL_AccelerometerRange::Type) {
+    ACCELEROMETER.with(|accelerometer| {
+        let mut accelerometer = accelerometer.borrow_mut();
+
+        accelerometer.range_set_while_active |= accelerometer.active;
+        accelerometer.range = range;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccelerometerX() -> f64 {
+    accelerometer().acceleration.0
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccelerometerY() -> f64 {
+    accelerometer().acceleration.1
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAccelerometerZ() -> f64 {
+    accelerometer().acceleration.2
+}
+
+pub const NUM_ANALOG_OUTPUTS: i32 = 2;
+
+#[derive(Clone, Debug, Default)]
+pub struct AnalogOutputState {
+    pub channel: i32,
+    pub voltage: f64,
+    pub freed: bool,
+}
+
+thread_local! {
+    static ANALOG_OUTPUTS: RefCell<HashMap<HAL_AnalogOutputHandle, AnalogOutputState>> = RefCell::new(HashMap::new());
+}
+
+pub fn analog_output(handle: HAL_AnalogOutputHandle) -> AnalogOutputState {
+    ANALOG_OUTPUTS.with(|outputs| outputs.borrow()[&handle].clone())
+}
+
+fn with_analog_output<T>(
+    handle: HAL_AnalogOutputHandle,
+    status: *mut i32,
+    f: impl FnOnce(&mut AnalogOutputState) -> T,
+) -> T
+where
+    T: Default,
+{
+    ANALOG_OUTPUTS.with(|outputs| match outputs.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.freed => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CheckAnalogOutputChannel(channel: i32) -> HAL_Bool {
+    (0..NUM_ANALOG_OUTPUTS).contains(&channel) as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeAnalogOutputPort(port: HAL_PortHandle, status: *mut i32) -> HAL_AnalogOutputHandle {
+    if HAL_CheckAnalogOutputChannel(port) == 0 {
+        unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        return HAL_kInvalidHandle;
+    }
+
+    let handle = port + 1;
+
+    ANALOG_OUTPUTS.with(|outputs| {
+        let mut outputs = outputs.borrow_mut();
+
+        if let Some(AnalogOutputState { freed: false, .. }) = outputs.get(&handle) {
+            unsafe { *status = RESOURCE_IS_ALLOCATED };
+            return HAL_kInvalidHandle;
+        }
+
+        let state = AnalogOutputState {
+            channel: port,
+            ..AnalogOutputState::default()
+        };
+
+        outputs.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FreeAnalogOutputPort(handle: HAL_AnalogOutputHandle) {
+    with_analog_output(handle, &mut 0, |state| state.freed = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetAnalogOutput(handle: HAL_AnalogOutputHandle, voltage: f64, status: *mut i32) {
+    // The HAL clamps rather than rejecting out of range voltages.
+    with_analog_output(handle, status, |state| state.voltage = voltage.clamp(0.0, 5.0))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetAnalogOutput(handle: HAL_AnalogOutputHandle, status: *mut i32) -> f64 {
+    with_analog_output(handle, status, |state| state.voltage)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RailState {
+    pub voltage: f64,
+    pub current: f64,
+    pub active: bool,
+    pub current_faults: i32,
+}
+
+/// The battery readings and the 6V, 5V and 3.3V rails, in that order.
+#[derive(Clone, Debug, Default)]
+pub struct PowerState {
+    pub battery_voltage: f64,
+    pub battery_current: f64,
+    pub browned_out: bool,
+    pub rails: [RailState; 3],
+}
+
+thread_local! {
+    static POWER: RefCell<PowerState> = RefCell::new(PowerState::default());
+}
+
+fn power() -> PowerState {
+    POWER.with(|power| power.borrow().clone())
+}
+
+pub fn set_battery(voltage: f64, current: f64, browned_out: bool) {
+    POWER.with(|power| {
+        let mut power = power.borrow_mut();
+
+        power.battery_voltage = voltage;
+        power.battery_current = current;
+        power.browned_out = browned_out;
+    })
+}
+
+pub fn set_power_rail(rail: usize, voltage: f64, current: f64, active: bool, current_faults: i32) {
+    let state = RailState {
+        voltage,
+        current,
+        active,
+        current_faults,
+    };
+
+    POWER.with(|power| power.borrow_mut().rails[rail] = state)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetVinVoltage(_status: *mut i32) -> f64 {
+    power().battery_voltage
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetVinCurrent(_status: *mut i32) -> f64 {
+    power().battery_current
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetBrownedOut(_status: *mut i32) -> HAL_Bool {
+    power().browned_out as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserVoltage6V(_status: *mut i32) -> f64 {
+    power().rails[0].voltage
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrent6V(_status: *mut i32) -> f64 {
+    power().rails[0].current
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserActive6V(_status: *mut i32) -> HAL_Bool {
+    power().rails[0].active as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrentFaults6V(_status: *mut i32) -> i32 {
+    power().rails[0].current_faults
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserVoltage5V(_status: *mut i32) -> f64 {
+    power().rails[1].voltage
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrent5V(_status: *mut i32) -> f64 {
+    power().rails[1].current
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserActive5V(_status: *mut i32) -> HAL_Bool {
+    power().rails[1].active as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrentFaults5V(_status: *mut i32) -> i32 {
+    power().rails[1].current_faults
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserVoltage3V3(_status: *mut i32) -> f64 {
+    power().rails[2].voltage
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrent3V3(_status: *mut i32) -> f64 {
+    power().rails[2].current
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserActive3V3(_status: *mut i32) -> HAL_Bool {
+    power().rails[2].active as HAL_Bool
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetUserCurrentFaults3V3(_status: *mut i32) -> i32 {
+    power().rails[2].current_faults
+}
+
+/// Acts like a process limited to this real-time priority, as one without privileges would be.
+pub const MAX_REAL_TIME_PRIORITY: i32 = 50;
+
+thread_local! {
+    static CURRENT_THREAD_PRIORITY: Cell<(HAL_Bool, i32)> = const { Cell::new((0, 0)) };
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetCurrentThreadPriority(real_time: *mut HAL_Bool, _status: *mut i32) -> i32 {
+    let (current_real_time, priority) = CURRENT_THREAD_PRIORITY.with(Cell::get);
+
+    unsafe { *real_time = current_real_time };
+    priority
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetCurrentThreadPriority(real_time: HAL_Bool, priority: i32, _status: *mut i32) -> HAL_Bool {
+    if real_time != 0 && !(1..=MAX_REAL_TIME_PRIORITY).contains(&priority) {
+        return 0;
+    }
+
+    CURRENT_THREAD_PRIORITY.with(|current| current.set((real_time, priority)));
+    1
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InterruptState {
+    pub index: i32,
+    pub watcher: bool,
+    pub rising_edge: bool,
+    pub falling_edge: bool,
+    pub pending_mask: i64,
+}
+
+thread_local! {
+    static INTERRUPTS: RefCell<HashMap<HAL_InterruptHandle, InterruptState>> = RefCell::new(HashMap::new());
+}
+
+pub fn interrupt(handle: HAL_InterruptHandle) -> InterruptState {
+    INTERRUPTS.with(|interrupts| interrupts.borrow()[&handle].clone())
+}
+
+/// Latches edges for the next `HAL_WaitForInterrupt`, in the bits the FPGA uses for the
+/// interrupt's index.
+pub fn fire_interrupt(handle: HAL_InterruptHandle, rising: bool, falling: bool) {
+    INTERRUPTS.with(|interrupts| {
+        let mut interrupts = interrupts.borrow_mut();
+        let state = interrupts.get_mut(&handle).unwrap();
+
+        if rising {
+            state.pending_mask |= 1 << state.index;
+        }
+
+        if falling {
+            state.pending_mask |= 1 << (state.index + 8);
+        }
+    })
+}
+
+fn with_interrupt<T>(handle: HAL_InterruptHandle, status: *mut i32, f: impl FnOnce(&mut InterruptState) -> T) -> T
+where
+    T: Default,
+{
+    INTERRUPTS.with(|interrupts| match interrupts.borrow_mut().get_mut(&handle) {
+        Some(state) => f(state),
+        None => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeInterrupts(watcher: HAL_Bool, _status: *mut i32) -> HAL_InterruptHandle {
+    INTERRUPTS.with(|interrupts| {
+        let mut interrupts = interrupts.borrow_mut();
+        let handle = interrupts.keys().max().map_or(1, |handle| handle + 1);
+        // The FPGA hands out the lowest free slot.
+        let index = (0..).find(|&index| interrupts.values().all(|state| state.index != index)).unwrap();
+        let state = InterruptState {
+            index,
+            watcher: watcher != 0,
+            ..InterruptState::default()
+        };
+
+        interrupts.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_RequestInterrupts(
+    handle: HAL_InterruptHandle,
+    _source: HAL_Handle,
+    _trigger_type: HAL_AnalogTriggerType::Type,
+    status: *mut i32,
+) {
+    with_interrupt(handle, status, |_| ())
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetInterruptUpSourceEdge(
+    handle: HAL_InterruptHandle,
+    rising: HAL_Bool,
+    falling: HAL_Bool,
+    status: *mut i32,
+) {
+    with_interrupt(handle, status, |state| {
+        state.rising_edge = rising != 0;
+        state.falling_edge = falling != 0;
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WaitForInterrupt(
+    handle: HAL_InterruptHandle,
+    _timeout: f64,
+    _ignore_previous: HAL_Bool,
+    status: *mut i32,
+) -> i64 {
+    // Nothing fires while waiting on one thread, so anything not already latched is a timeout.
+    with_interrupt(handle, status, |state| std::mem::take(&mut state.pending_mask))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CleanInterrupts(handle: HAL_InterruptHandle, status: *mut i32) -> *mut c_void {
+    if INTERRUPTS.with(|interrupts| interrupts.borrow_mut().remove(&handle)).is_none() {
+        unsafe { *status = HAL_HANDLE_ERROR };
+    }
+
+    std::ptr::null_mut()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CanDeviceState {
+    pub manufacturer: HAL_CANManufacturer::Type,
+    pub device_type: HAL_CANDeviceType::Type,
+    pub device_number: i32,
+    /// Packets written once, as (api id, data).
+    pub sent: Vec<(i32, Vec<u8>)>,
+    /// Packets being resent, as api id to (data, period in milliseconds).
+    pub repeating: HashMap<i32, (Vec<u8>, i32)>,
+    /// Remote frames requested, as (api id, length).
+    pub rtr_frames: Vec<(i32, i32)>,
+    /// The last frame received for each api id, as (data, timestamp, unread).
+    pub received: HashMap<i32, (Vec<u8>, u64, bool)>,
+    pub cleaned: bool,
+}
+
+thread_local! {
+    static CAN_DEVICES: RefCell<HashMap<HAL_CANHandle, CanDeviceState>> = RefCell::new(HashMap::new());
+    static CAN_TIME_MS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn can_device(handle: HAL_CANHandle) -> CanDeviceState {
+    CAN_DEVICES.with(|devices| devices.borrow()[&handle].clone())
+}
+
+/// Stores a frame as if the device sent it now.
+pub fn receive_can_frame(handle: HAL_CANHandle, api_id: i32, data: &[u8]) {
+    let timestamp = CAN_TIME_MS.with(Cell::get);
+
+    with_can_device(handle, &mut 0, |state| {
+        state.received.insert(api_id, (data.to_vec(), timestamp, true));
+    })
+}
+
+pub fn advance_can_time(millis: u64) {
+    CAN_TIME_MS.with(|now| now.set(now.get() + millis))
+}
+
+fn with_can_device<T>(handle: HAL_CANHandle, status: *mut i32, f: impl FnOnce(&mut CanDeviceState) -> T) -> T
+where
+    T: Default,
+{
+    CAN_DEVICES.with(|devices| match devices.borrow_mut().get_mut(&handle) {
+        Some(state) if !state.cleaned => f(state),
+        _ => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeCAN(
+    manufacturer: HAL_CANManufacturer::Type,
+    device_number: i32,
+    device_type: HAL_CANDeviceType::Type,
+    _status: *mut i32,
+) -> HAL_CANHandle {
+    CAN_DEVICES.with(|devices| {
+        let mut devices = devices.borrow_mut();
+        let handle = devices.keys().max().map_or(1, |handle| handle + 1);
+        let state = CanDeviceState {
+            manufacturer,
+            device_type,
+            device_number,
+            ..CanDeviceState::default()
+        };
+
+        devices.insert(handle, state);
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CleanCAN(handle: HAL_CANHandle) {
+    with_can_device(handle, &mut 0, |state| state.cleaned = true)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WriteCANPacket(
+    handle: HAL_CANHandle,
+    data: *const u8,
+    length: i32,
+    api_id: i32,
+    status: *mut i32,
+) {
+    let data = unsafe { std::slice::from_raw_parts(data, length as usize) };
+
+    with_can_device(handle, status, |state| {
+        state.repeating.remove(&api_id);
+        state.sent.push((api_id, data.to_vec()));
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WriteCANPacketRepeating(
+    handle: HAL_CANHandle,
+    data: *const u8,
+    length: i32,
+    api_id: i32,
+    repeat_ms: i32,
+    status: *mut i32,
+) {
+    let data = unsafe { std::slice::from_raw_parts(data, length as usize) };
+
+    with_can_device(handle, status, |state| {
+        state.sent.push((api_id, data.to_vec()));
+        state.repeating.insert(api_id, (data.to_vec(), repeat_ms));
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WriteCANRTRFrame(handle: HAL_CANHandle, length: i32, api_id: i32, status: *mut i32) {
+    with_can_device(handle, status, |state| state.rtr_frames.push((api_id, length)))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_StopCANPacketRepeating(handle: HAL_CANHandle, api_id: i32, status: *mut i32) {
+    with_can_device(handle, status, |state| {
+        state.repeating.remove(&api_id);
+    })
+}
+
+// Takes the frame for `api_id` if `accept` allows it, otherwise reports `missing`.
+fn take_can_frame(
+    handle: HAL_CANHandle,
+    api_id: i32,
+    status: *mut i32,
+    missing: i32,
+    accept: impl FnOnce(u64, bool) -> bool,
+) -> Option<(Vec<u8>, u64)> {
+    with_can_device(handle, status, |state| match state.received.get_mut(&api_id) {
+        Some((frame, received, unread)) if accept(*received, *unread) => {
+            *unread = false;
+            Some((frame.clone(), *received))
+        }
+        _ => {
+            unsafe { *status = missing };
+            None
+        }
+    })
+}
+
+fn copy_can_frame(frame: Option<(Vec<u8>, u64)>, data: *mut u8, length: *mut i32, timestamp: *mut u64) {
+    if let Some((frame, received)) = frame {
+        unsafe {
+            std::ptr::copy_nonoverlapping(frame.as_ptr(), data, frame.len());
+            *length = frame.len() as i32;
+            *timestamp = received;
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadCANPacketNew(
+    handle: HAL_CANHandle,
+    api_id: i32,
+    data: *mut u8,
+    length: *mut i32,
+    timestamp: *mut u64,
+    status: *mut i32,
+) {
+    let frame = take_can_frame(handle, api_id, status, HAL_ERR_CANSessionMux_MessageNotFound, |_, unread| unread);
+
+    copy_can_frame(frame, data, length, timestamp)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadCANPacketLatest(
+    handle: HAL_CANHandle,
+    api_id: i32,
+    data: *mut u8,
+    length: *mut i32,
+    timestamp: *mut u64,
+    status: *mut i32,
+) {
+    let frame = take_can_frame(handle, api_id, status, HAL_ERR_CANSessionMux_MessageNotFound, |_, _| true);
+
+    copy_can_frame(frame, data, length, timestamp)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadCANPacketTimeout(
+    handle: HAL_CANHandle,
+    api_id: i32,
+    data: *mut u8,
+    length: *mut i32,
+    timestamp: *mut u64,
+    timeout_ms: i32,
+    status: *mut i32,
+) {
+    let now = CAN_TIME_MS.with(Cell::get);
+
+    let frame = take_can_frame(handle, api_id, status, HAL_CAN_TIMEOUT, |received, _| {
+        now - received <= timeout_ms as u64
+    });
+
+    copy_can_frame(frame, data, length, timestamp)
+}
+
+#[derive(Debug, Default)]
+pub struct CanStreamState {
+    pub messages: Vec<HAL_CANStreamMessage>,
+    pub read_status: i32,
+}
+
+thread_local! {
+    static CAN_STREAMS: RefCell<HashMap<u32, CanStreamState>> = RefCell::new(HashMap::new());
+}
+
+pub fn with_can_stream<T>(session: u32, f: impl FnOnce(&mut CanStreamState) -> T) -> T {
+    CAN_STREAMS.with(|streams| f(streams.borrow_mut().get_mut(&session).unwrap()))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CAN_OpenStreamSession(
+    session: *mut u32,
+    _message_id: u32,
+    _message_id_mask: u32,
+    _max_messages: u32,
+    _status: *mut i32,
+) {
+    CAN_STREAMS.with(|streams| {
+        let mut streams = streams.borrow_mut();
+        let handle = streams.keys().max().map_or(1, |handle| handle + 1);
+
+        streams.insert(handle, CanStreamState::default());
+        unsafe { *session = handle };
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CAN_ReadStreamSession(
+    session: u32,
+    messages: *mut HAL_CANStreamMessage,
+    messages_to_read: u32,
+    messages_read: *mut u32,
+    status: *mut i32,
+) {
+    let messages = unsafe { std::slice::from_raw_parts_mut(messages, messages_to_read as usize) };
+
+    with_can_stream(session, |state| {
+        let count = state.messages.len().min(messages.len());
+
+        for (slot, message) in messages.iter_mut().zip(state.messages.drain(..count)) {
+            *slot = message;
+        }
+
+        unsafe {
+            *messages_read = count as u32;
+            *status = match state.read_status {
+                0 if count == 0 => HAL_ERR_CANSessionMux_MessageNotFound,
+                read_status => read_status,
+            };
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CAN_CloseStreamSession(session: u32) {
+    CAN_STREAMS.with(|streams| streams.borrow_mut().remove(&session));
+}
+
+const O_RDWR: c_int = 0o2;
+const O_NOCTTY: c_int = 0o400;
+const TCSANOW: c_int = 0;
+const TCIFLUSH: c_int = 0;
+const FIONREAD: c_ulong = 0x541B;
+const POLLIN: c_short = 0x1;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+extern "C" {
+    fn posix_openpt(flags: c_int) -> c_int;
+    fn grantpt(fd: c_int) -> c_int;
+    fn unlockpt(fd: c_int) -> c_int;
+    fn ptsname_r(fd: c_int, buf: *mut c_char, len: usize) -> c_int;
+    fn tcgetattr(fd: c_int, termios: *mut u8) -> c_int;
+    fn tcsetattr(fd: c_int, action: c_int, termios: *const u8) -> c_int;
+    fn tcflush(fd: c_int, queue: c_int) -> c_int;
+    fn cfmakeraw(termios: *mut u8);
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+}
+
+/// Opens a pseudo-terminal, returning the master side and the path of the slave side to hand to
+/// `HAL_InitializeSerialPortDirect`.
+pub fn open_pty() -> (File, String) {
+    unsafe {
+        let fd = posix_openpt(O_RDWR | O_NOCTTY);
+        assert!(fd >= 0 && grantpt(fd) == 0 && unlockpt(fd) == 0, "failed to open a pty");
+
+        let mut name = [0 as c_char; 128];
+        assert_eq!(ptsname_r(fd, name.as_mut_ptr(), name.len()), 0);
+
+        (File::from_raw_fd(fd), CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SerialState {
+    pub path: String,
+    pub baud_rate: i32,
+    pub data_bits: i32,
+    pub parity: i32,
+    pub stop_bits: i32,
+    pub write_mode: i32,
+    pub flow_control: i32,
+    pub timeout: f64,
+    pub termination: Option<c_char>,
+    pub read_buffer_size: i32,
+    pub write_buffer_size: i32,
+}
+
+thread_local! {
+    static SERIALS: RefCell<HashMap<HAL_SerialPortHandle, (SerialState, File)>> = RefCell::new(HashMap::new());
+}
+
+pub fn serial(handle: HAL_SerialPortHandle) -> SerialState {
+    SERIALS.with(|serials| serials.borrow()[&handle].0.clone())
+}
+
+pub fn open_serial_ports() -> usize {
+    SERIALS.with(|serials| serials.borrow().len())
+}
+
+fn with_serial<T>(handle: HAL_SerialPortHandle, status: *mut i32, f: impl FnOnce(&mut SerialState, &mut File) -> T) -> T
+where
+    T: Default,
+{
+    SERIALS.with(|serials| match serials.borrow_mut().get_mut(&handle) {
+        Some((state, file)) => f(state, file),
+        None => {
+            unsafe { *status = HAL_HANDLE_ERROR };
+            T::default()
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeSerialPort(_port: HAL_SerialPort::Type, status: *mut i32) -> HAL_SerialPortHandle {
+    // There is no roboRIO device to open, tests go through a pty instead.
+    unsafe { *status = NO_AVAILABLE_RESOURCES };
+    HAL_kInvalidHandle
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_InitializeSerialPortDirect(
+    _port: HAL_SerialPort::Type,
+    port_name: *const c_char,
+    status: *mut i32,
+) -> HAL_SerialPortHandle {
+    let path = unsafe { CStr::from_ptr(port_name) }.to_string_lossy().into_owned();
+    let file = match OpenOptions::new().read(true).write(true).custom_flags(O_NOCTTY).open(&path) {
+        Ok(file) => file,
+        Err(_) => {
+            unsafe { *status = NO_AVAILABLE_RESOURCES };
+            return HAL_kInvalidHandle;
+        }
+    };
+
+    // Like the real HAL, put the terminal in raw mode so bytes pass through untouched.
+    let mut termios = [0u8; 256];
+    unsafe {
+        tcgetattr(file.as_raw_fd(), termios.as_mut_ptr());
+        cfmakeraw(termios.as_mut_ptr());
+        tcsetattr(file.as_raw_fd(), TCSANOW, termios.as_ptr());
+    }
+
+    SERIALS.with(|serials| {
+        let mut serials = serials.borrow_mut();
+        let handle = serials.keys().max().map_or(1, |handle| handle + 1);
+
+        // WPILib opens ports with a 5 second timeout.
+        let state = SerialState {
+            path,
+            timeout: 5000.0,
+            ..SerialState::default()
+        };
+
+        serials.insert(handle, (state, file));
+        handle
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialBaudRate(handle: HAL_SerialPortHandle, baud: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.baud_rate = baud)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialDataBits(handle: HAL_SerialPortHandle, bits: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| {
+        if (5..=8).contains(&bits) {
+            state.data_bits = bits;
+        } else {
+            unsafe { *status = PARAMETER_OUT_OF_RANGE };
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialParity(handle: HAL_SerialPortHandle, parity: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.parity = parity)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialStopBits(handle: HAL_SerialPortHandle, stop_bits: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.stop_bits = stop_bits)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialWriteMode(handle: HAL_SerialPortHandle, mode: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.write_mode = mode)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialFlowControl(handle: HAL_SerialPortHandle, flow: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.flow_control = flow)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialTimeout(handle: HAL_SerialPortHandle, timeout: f64, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.timeout = timeout)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_EnableSerialTermination(handle: HAL_SerialPortHandle, terminator: c_char, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.termination = Some(terminator))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_DisableSerialTermination(handle: HAL_SerialPortHandle, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.termination = None)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialReadBufferSize(handle: HAL_SerialPortHandle, size: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.read_buffer_size = size)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_SetSerialWriteBufferSize(handle: HAL_SerialPortHandle, size: i32, status: *mut i32) {
+    with_serial(handle, status, |state, _| state.write_buffer_size = size)
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_GetSerialBytesReceived(handle: HAL_SerialPortHandle, status: *mut i32) -> i32 {
+    with_serial(handle, status, |_, file| {
+        let mut available: c_int = 0;
+        unsafe { ioctl(file.as_raw_fd(), FIONREAD, &mut available as *mut c_int) };
+        available
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ReadSerial(handle: HAL_SerialPortHandle, buffer: *mut c_char, count: i32, status: *mut i32) -> i32 {
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer as *mut u8, count as usize) };
+
+    with_serial(handle, status, |state, file| {
+        let mut poll_fd = PollFd {
+            fd: file.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        };
+
+        // Like the real HAL, a read that times out returns no bytes without an error.
+        match unsafe { poll(&mut poll_fd, 1, state.timeout as c_int) } {
+            ready if ready > 0 => file.read(buffer).map_or(0, |read| read as i32),
+            _ => 0,
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_WriteSerial(handle: HAL_SerialPortHandle, buffer: *const c_char, count: i32, status: *mut i32) -> i32 {
+    let buffer = unsafe { std::slice::from_raw_parts(buffer as *const u8, count as usize) };
+
+    with_serial(handle, status, |_, file| file.write(buffer).map_or(0, |written| written as i32))
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_FlushSerial(handle: HAL_SerialPortHandle, status: *mut i32) {
+    with_serial(handle, status, |_, file| {
+        let _ = file.flush();
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_ClearSerial(handle: HAL_SerialPortHandle, status: *mut i32) {
+    with_serial(handle, status, |_, file| {
+        unsafe { tcflush(file.as_raw_fd(), TCIFLUSH) };
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn HAL_CloseSerial(handle: HAL_SerialPortHandle, status: *mut i32) {
+    if SERIALS.with(|serials| serials.borrow_mut().remove(&handle)).is_none() {
+        unsafe { *status = HAL_HANDLE_ERROR };
+    }
+}
\ No newline at end of file
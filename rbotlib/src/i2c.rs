@@ -0,0 +1,226 @@
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum I2cPort {
+    Onboard,
+    Mxp,
+}
+
+impl From<I2cPort> for HAL_I2CPort::Type {
+    fn from(port: I2cPort) -> HAL_I2CPort::Type {
+        match port {
+            I2cPort::Onboard => HAL_I2CPort::HAL_I2C_kOnboard,
+            I2cPort::Mxp => HAL_I2CPort::HAL_I2C_kMXP,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum I2cError {
+    TransferAborted,
+    EmptyBuffer,
+}
+
+#[derive(Debug)]
+pub struct I2c {
+    port: I2cPort,
+    device_address: i32,
+}
+
+impl I2c {
+    pub fn new(port: I2cPort, device_address: u8) -> HalResult<I2c> {
+        hal_call!(HAL_InitializeI2C(port.into()))?;
+
+        Ok(I2c {
+            port,
+            device_address: i32::from(device_address),
+        })
+    }
+
+    pub fn get_port(&self) -> I2cPort {
+        self.port
+    }
+
+    pub fn get_device_address(&self) -> u8 {
+        self.device_address as u8
+    }
+
+    fn check(result: i32) -> Result<(), I2cError> {
+        if result < 0 {
+            Err(I2cError::TransferAborted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes `send` then reads into `receive` as one transaction, without releasing the bus.
+    pub fn transaction(&mut self, send: &[u8], receive: &mut [u8]) -> Result<(), I2cError> {
+        I2c::check(unsafe {
+            HAL_TransactionI2C(
+                self.port.into(),
+                self.device_address,
+                send.as_ptr(),
+                send.len() as i32,
+                receive.as_mut_ptr(),
+                receive.len() as i32,
+            )
+        })
+    }
+
+    /// Sends only the address, succeeding if a device acknowledges it.
+    pub fn address_only(&mut self) -> Result<(), I2cError> {
+        self.transaction(&[], &mut [])
+    }
+
+    pub fn write_bulk(&mut self, data: &[u8]) -> Result<(), I2cError> {
+        I2c::check(unsafe { HAL_WriteI2C(self.port.into(), self.device_address, data.as_ptr(), data.len() as i32) })
+    }
+
+    pub fn read_only(&mut self, buffer: &mut [u8]) -> Result<(), I2cError> {
+        if buffer.is_empty() {
+            return Err(I2cError::EmptyBuffer);
+        }
+
+        I2c::check(unsafe {
+            HAL_ReadI2C(self.port.into(), self.device_address, buffer.as_mut_ptr(), buffer.len() as i32)
+        })
+    }
+
+    /// Reads consecutive registers starting at `register`, relying on the device auto-incrementing.
+    pub fn read(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), I2cError> {
+        if buffer.is_empty() {
+            return Err(I2cError::EmptyBuffer);
+        }
+
+        self.transaction(&[register], buffer)
+    }
+
+    pub fn write(&mut self, register: u8, data: &[u8]) -> Result<(), I2cError> {
+        let mut buffer = Vec::with_capacity(data.len() + 1);
+        buffer.push(register);
+        buffer.extend_from_slice(data);
+
+        self.write_bulk(&buffer)
+    }
+
+    pub fn read_register(&mut self, register: u8) -> Result<u8, I2cError> {
+        let mut value = [0];
+
+        self.read(register, &mut value)?;
+        Ok(value[0])
+    }
+
+    pub fn write_register(&mut self, register: u8, value: u8) -> Result<(), I2cError> {
+        self.write_bulk(&[register, value])
+    }
+
+    /// Reads a register, replaces the bits in `mask` with those of `value` and writes it back.
+    pub fn update_register(&mut self, register: u8, mask: u8, value: u8) -> Result<(), I2cError> {
+        let current = self.read_register(register)?;
+
+        self.write_register(register, (current & !mask) | (value & mask))
+    }
+
+    pub fn read_register_u16_le(&mut self, register: u8) -> Result<u16, I2cError> {
+        let mut value = [0; 2];
+
+        self.read(register, &mut value)?;
+        Ok(u16::from_le_bytes(value))
+    }
+
+    pub fn read_register_u16_be(&mut self, register: u8) -> Result<u16, I2cError> {
+        let mut value = [0; 2];
+
+        self.read(register, &mut value)?;
+        Ok(u16::from_be_bytes(value))
+    }
+
+    /// Checks that the registers starting at `register` hold `expected`, such as a device id,
+    /// to make sure the right sensor is on the bus.
+    pub fn verify_sensor(&mut self, register: u8, expected: &[u8]) -> Result<bool, I2cError> {
+        let mut actual = vec![0; expected.len()];
+
+        self.read(register, &mut actual)?;
+        Ok(actual == expected)
+    }
+}
+
+impl Drop for I2c {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_CloseI2C(self.port.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    const ADDRESS: u8 = 0x1d;
+    const ONBOARD: HAL_I2CPort::Type = HAL_I2CPort::HAL_I2C_kOnboard;
+
+    fn device(registers: &[(u8, u8)]) -> I2c {
+        hal_stub::add_i2c_device(ONBOARD, i32::from(ADDRESS), registers);
+
+        I2c::new(I2cPort::Onboard, ADDRESS).unwrap()
+    }
+
+    #[test]
+    fn missing_device_aborts() {
+        let mut i2c = I2c::new(I2cPort::Mxp, 0x50).unwrap();
+
+        assert_eq!(i2c.address_only(), Err(I2cError::TransferAborted));
+        assert_eq!(i2c.read_register(0), Err(I2cError::TransferAborted));
+        assert_eq!(i2c.write_register(0, 1), Err(I2cError::TransferAborted));
+    }
+
+    #[test]
+    fn empty_reads_are_rejected() {
+        let mut i2c = device(&[]);
+
+        assert_eq!(i2c.read(0, &mut []), Err(I2cError::EmptyBuffer));
+        assert_eq!(i2c.read_only(&mut []), Err(I2cError::EmptyBuffer));
+        assert_eq!(i2c.address_only(), Ok(()));
+    }
+
+    #[test]
+    fn registers_read_and_write() {
+        let mut i2c = device(&[(0x00, 0xe5), (0x32, 0x34), (0x33, 0x12)]);
+
+        assert_eq!(i2c.read_register(0x00), Ok(0xe5));
+        assert_eq!(i2c.read_register_u16_le(0x32), Ok(0x1234));
+        assert_eq!(i2c.read_register_u16_be(0x32), Ok(0x3412));
+        assert_eq!(i2c.verify_sensor(0x00, &[0xe5]), Ok(true));
+        assert_eq!(i2c.verify_sensor(0x00, &[0xe6]), Ok(false));
+
+        i2c.write(0x1e, &[1, 2, 3]).unwrap();
+        let mut buffer = [0; 3];
+        i2c.read(0x1e, &mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3]);
+
+        // The register pointer was left after the last byte read.
+        i2c.read_only(&mut buffer[..1]).unwrap();
+        assert_eq!(buffer[0], 0);
+    }
+
+    #[test]
+    fn update_register_keeps_unmasked_bits() {
+        let mut i2c = device(&[(0x2d, 0b1010_0101)]);
+
+        i2c.update_register(0x2d, 0b0000_1111, 0b1111_1000).unwrap();
+        assert_eq!(hal_stub::i2c_register(ONBOARD, i32::from(ADDRESS), 0x2d), 0b1010_1000);
+    }
+
+    #[test]
+    fn drop_closes_the_port() {
+        let first = I2c::new(I2cPort::Onboard, 0x10).unwrap();
+        let second = I2c::new(I2cPort::Onboard, 0x11).unwrap();
+        assert_eq!(hal_stub::i2c_open_count(ONBOARD), 2);
+
+        drop(first);
+        drop(second);
+        assert_eq!(hal_stub::i2c_open_count(ONBOARD), 0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,295 @@
+use std::fmt;
+use std::future::Future;
+use std::os::raw::c_void;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rbothal::*;
+
+use crate::counter::Edge;
+use crate::dio::DigitalSource;
+
+// The HAL sets bit `index` for a rising edge and bit `index + 8` for a falling edge, where
+// index is the interrupt's own slot.
+const RISING_MASK: i64 = 0xff;
+const FALLING_MASK: i64 = 0xff00;
+
+// How long the future's waiting thread blocks at a time before checking if it was dropped.
+const FUTURE_POLL_INTERVAL: f64 = 0.05;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WaitResult {
+    Timeout,
+    RisingEdge,
+    FallingEdge,
+    /// Both edges happened since the last wait.
+    Both,
+}
+
+impl WaitResult {
+    fn from_mask(mask: i64) -> WaitResult {
+        match (mask & RISING_MASK != 0, mask & FALLING_MASK != 0) {
+            (false, false) => WaitResult::Timeout,
+            (true, false) => WaitResult::RisingEdge,
+            (false, true) => WaitResult::FallingEdge,
+            (true, true) => WaitResult::Both,
+        }
+    }
+}
+
+type Callback = Box<dyn FnMut(WaitResult) + Send>;
+
+unsafe extern "C" fn call_callback(mask: u32, param: *mut c_void) {
+    let callback = &mut *(param as *mut Callback);
+
+    callback(WaitResult::from_mask(i64::from(mask)));
+}
+
+/// Interrupts on the edges of a digital source, either waited on or handled by a callback.
+pub struct Interrupt {
+    handle: HAL_InterruptHandle,
+    // The FPGA keeps reading from this, so it must outlive `handle`.
+    _source: Box<dyn DigitalSource>,
+    // Passed to the HAL by pointer, so it is double boxed to keep the address stable.
+    callback: Option<Box<Callback>>,
+}
+
+impl fmt::Debug for Interrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interrupt")
+            .field("handle", &self.handle)
+            .field("source", &self._source)
+            .field("has_callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+impl Interrupt {
+    fn initialize(source: Box<dyn DigitalSource>, edge: Edge, watcher: bool) -> HalResult<Interrupt> {
+        let handle = hal_call!(HAL_InitializeInterrupts(watcher as HAL_Bool))?;
+        let interrupt = Interrupt {
+            handle,
+            _source: source,
+            callback: None,
+        };
+
+        hal_call!(HAL_RequestInterrupts(
+            handle,
+            interrupt._source.get_port_handle_for_routing(),
+            interrupt._source.get_analog_trigger_type_for_routing(),
+        ))?;
+        hal_call!(HAL_SetInterruptUpSourceEdge(handle, edge.rising(), edge.falling()))?;
+        Ok(interrupt)
+    }
+
+    /// Creates an interrupt to wait on with `wait` or `wait_for_edge`.
+    pub fn new<S>(source: S, edge: Edge) -> HalResult<Interrupt>
+    where
+        S: DigitalSource + 'static,
+    {
+        Interrupt::initialize(Box::new(source), edge, true)
+    }
+
+    /// Creates an interrupt that calls `callback` on its own thread for every edge.
+    pub fn with_callback<S, F>(source: S, edge: Edge, callback: F) -> HalResult<Interrupt>
+    where
+        S: DigitalSource + 'static,
+        F: FnMut(WaitResult) + Send + 'static,
+    {
+        let mut interrupt = Interrupt::initialize(Box::new(source), edge, false)?;
+        let mut callback: Box<Callback> = Box::new(Box::new(callback));
+
+        hal_call!(HAL_AttachInterruptHandlerThreaded(
+            interrupt.handle,
+            Some(call_callback),
+            &mut *callback as *mut Callback as *mut c_void,
+        ))?;
+        interrupt.callback = Some(callback);
+        interrupt.enable()?;
+        Ok(interrupt)
+    }
+
+    pub fn set_edge(&mut self, edge: Edge) -> HalResult<()> {
+        hal_call!(HAL_SetInterruptUpSourceEdge(self.handle, edge.rising(), edge.falling()))
+    }
+
+    pub fn enable(&mut self) -> HalResult<()> {
+        hal_call!(HAL_EnableInterrupts(self.handle))
+    }
+
+    pub fn disable(&mut self) -> HalResult<()> {
+        hal_call!(HAL_DisableInterrupts(self.handle))
+    }
+
+    /// Blocks until an edge or `timeout`. When `ignore_previous` is false an edge that happened
+    /// since the last wait returns immediately.
+    pub fn wait(&self, timeout: Duration, ignore_previous: bool) -> HalResult<WaitResult> {
+        if self.callback.is_some() {
+            return Err(HalError(INCOMPATIBLE_STATE));
+        }
+
+        wait_for_interrupt(self.handle, timeout.as_secs_f64(), ignore_previous)
+    }
+
+    /// Resolves on the next edge, ignoring any that happened before.
+    pub fn wait_for_edge(&self) -> InterruptFuture<'_> {
+        InterruptFuture {
+            interrupt: self,
+            shared: Arc::new(Mutex::new(FutureState::default())),
+            cancelled: Arc::new(AtomicBool::new(false)),
+            thread: None,
+        }
+    }
+
+    /// Gets the FPGA time in microseconds of the last rising edge. Only the lower 32 bits are
+    /// kept, so it wraps about every 71 minutes.
+    pub fn get_rising_timestamp(&self) -> HalResult<i64> {
+        hal_call!(HAL_ReadInterruptRisingTimestamp(self.handle))
+    }
+
+    /// Gets the FPGA time in microseconds of the last falling edge. Only the lower 32 bits are
+    /// kept, so it wraps about every 71 minutes.
+    pub fn get_falling_timestamp(&self) -> HalResult<i64> {
+        hal_call!(HAL_ReadInterruptFallingTimestamp(self.handle))
+    }
+}
+
+impl Drop for Interrupt {
+    fn drop(&mut self) {
+        // Stops the handler thread before the callback is freed.
+        let _ = hal_call!(HAL_CleanInterrupts(self.handle));
+    }
+}
+
+fn wait_for_interrupt(handle: HAL_InterruptHandle, timeout: f64, ignore_previous: bool) -> HalResult<WaitResult> {
+    let mask = hal_call!(HAL_WaitForInterrupt(handle, timeout, ignore_previous as HAL_Bool))?;
+
+    Ok(WaitResult::from_mask(mask))
+}
+
+#[derive(Debug, Default)]
+struct FutureState {
+    result: Option<HalResult<WaitResult>>,
+    waker: Option<Waker>,
+}
+
+/// Waits for an edge on a background thread, see `Interrupt::wait_for_edge`.
+#[derive(Debug)]
+pub struct InterruptFuture<'a> {
+    interrupt: &'a Interrupt,
+    shared: Arc<Mutex<FutureState>>,
+    cancelled: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl<'a> Future for InterruptFuture<'a> {
+    type Output = HalResult<WaitResult>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<HalResult<WaitResult>> {
+        {
+            let mut state = self.shared.lock().unwrap();
+
+            if let Some(result) = state.result.take() {
+                return Poll::Ready(result);
+            }
+
+            state.waker = Some(cx.waker().clone());
+        }
+
+        if self.interrupt.callback.is_some() {
+            return Poll::Ready(Err(HalError(INCOMPATIBLE_STATE)));
+        }
+
+        if self.thread.is_none() {
+            let handle = self.interrupt.handle;
+            let shared = Arc::clone(&self.shared);
+            let cancelled = Arc::clone(&self.cancelled);
+
+            let spawned = thread::Builder::new().name("InterruptFuture".to_owned()).spawn(move || {
+                let mut ignore_previous = true;
+
+                let result = loop {
+                    match wait_for_interrupt(handle, FUTURE_POLL_INTERVAL, ignore_previous) {
+                        Ok(WaitResult::Timeout) if !cancelled.load(Ordering::Acquire) => ignore_previous = false,
+                        result => break result,
+                    }
+                };
+
+                let mut state = shared.lock().unwrap();
+                state.result = Some(result);
+
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            });
+
+            match spawned {
+                Ok(thread) => self.thread = Some(thread),
+                Err(_) => return Poll::Ready(Err(HalError(NO_AVAILABLE_RESOURCES))),
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<'a> Drop for InterruptFuture<'a> {
+    fn drop(&mut self) {
+        // The thread waits on the interrupt's handle, so it has to finish before the borrow ends.
+        self.cancelled.store(true, Ordering::Release);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[derive(Debug)]
+    struct StubSource;
+
+    impl DigitalSource for StubSource {
+        fn get_port_handle_for_routing(&self) -> HAL_Handle {
+            0
+        }
+
+        fn get_analog_trigger_type_for_routing(&self) -> HAL_AnalogTriggerType::Type {
+            0
+        }
+    }
+
+    #[test]
+    fn masks_decode_for_any_index() {
+        for index in 0..8 {
+            assert_eq!(WaitResult::from_mask(0), WaitResult::Timeout);
+            assert_eq!(WaitResult::from_mask(1 << index), WaitResult::RisingEdge);
+            assert_eq!(WaitResult::from_mask(1 << (index + 8)), WaitResult::FallingEdge);
+            assert_eq!(WaitResult::from_mask(1 << index | 1 << (index + 8)), WaitResult::Both);
+        }
+    }
+
+    #[test]
+    fn wait_sees_edges_on_a_later_interrupt() {
+        let _first = Interrupt::new(StubSource, Edge::Both).unwrap();
+        let second = Interrupt::new(StubSource, Edge::Both).unwrap();
+        let state = hal_stub::interrupt(second.handle);
+        assert_eq!(state.index, 1);
+        assert!(state.watcher && state.rising_edge && state.falling_edge);
+
+        hal_stub::fire_interrupt(second.handle, true, false);
+        assert_eq!(second.wait(Duration::from_millis(10), false).unwrap(), WaitResult::RisingEdge);
+
+        hal_stub::fire_interrupt(second.handle, false, true);
+        assert_eq!(second.wait(Duration::from_millis(10), false).unwrap(), WaitResult::FallingEdge);
+
+        assert_eq!(second.wait(Duration::from_millis(10), false).unwrap(), WaitResult::Timeout);
+    }
+}
\ No newline at end of file
@@ -8,4 +8,32 @@ pub mod robot_base;
 pub mod robot_state;
 pub mod driverstation;
 pub mod fpga;
-pub mod joystick;
\ No newline at end of file
+pub mod joystick;
+pub mod speed_controller;
+pub mod pwm;
+pub mod dio;
+pub mod encoder;
+pub mod analog;
+pub mod analog_output;
+pub mod gyro;
+pub mod analog_gyro;
+pub mod pneumatics;
+pub mod pdp;
+pub mod relay;
+pub mod counter;
+pub mod analog_trigger;
+pub mod i2c;
+pub mod spi;
+pub mod serial;
+pub mod notifier;
+pub mod interrupt;
+pub mod accelerometer;
+pub mod builtin_accelerometer;
+pub mod threads;
+pub mod can;
+pub mod can_log;
+pub mod spark_max;
+pub mod ctre;
+
+#[cfg(test)]
+mod hal_stub;
\ No newline at end of file
@@ -0,0 +1,130 @@
+use std::ffi::CString;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rbothal::*;
+
+use crate::fpga;
+use crate::threads::{self, ThreadPriority};
+
+#[derive(Debug, Default)]
+struct Alarm {
+    /// Next trigger time in FPGA microseconds.
+    expiration: u64,
+    /// Period in microseconds, or `None` when only a single callback is scheduled.
+    period: Option<u64>,
+}
+
+#[derive(Debug)]
+struct NotifierShared {
+    handle: HAL_NotifierHandle,
+    alarm: Mutex<Alarm>,
+}
+
+/// Runs a callback on its own thread at times scheduled against the FPGA clock.
+#[derive(Debug)]
+pub struct Notifier {
+    shared: Arc<NotifierShared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Notifier {
+    pub fn new<F>(mut callback: F) -> HalResult<Notifier>
+    where
+        F: FnMut() + Send + 'static,
+    {
+        let shared = Arc::new(NotifierShared {
+            handle: hal_call!(HAL_InitializeNotifier())?,
+            alarm: Mutex::new(Alarm::default()),
+        });
+        let thread_shared = Arc::clone(&shared);
+
+        let spawned = thread::Builder::new().name("Notifier".to_owned()).spawn(move || loop {
+            // Returns 0 once the notifier is stopped on drop.
+            match hal_call!(HAL_WaitForNotifierAlarm(thread_shared.handle)) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            {
+                let mut alarm = thread_shared.alarm.lock().unwrap();
+
+                if let Some(period) = alarm.period {
+                    // Advance from the previous expiration rather than now so the period doesn't drift.
+                    alarm.expiration += period;
+                    let _ = hal_call!(HAL_UpdateNotifierAlarm(thread_shared.handle, alarm.expiration));
+                }
+            }
+
+            callback();
+        });
+
+        let thread = match spawned {
+            Ok(thread) => thread,
+            Err(_) => {
+                // Nothing is waiting on the alarm yet, so the handle can be released directly.
+                let _ = hal_call!(HAL_CleanNotifier(shared.handle));
+                return Err(HalError(NO_AVAILABLE_RESOURCES));
+            }
+        };
+
+        Ok(Notifier {
+            shared,
+            thread: Some(thread),
+        })
+    }
+
+    /// Names the notifier's thread in the HAL's diagnostics.
+    pub fn set_name(&mut self, name: &str) -> HalResult<()> {
+        let name = CString::new(name).map_err(|_| HalError(PARAMETER_OUT_OF_RANGE))?;
+
+        hal_call!(HAL_SetNotifierName(self.shared.handle, name.as_ptr()))
+    }
+
+    /// Sets the priority of the thread running the callback.
+    pub fn set_thread_priority(&mut self, priority: ThreadPriority) -> HalResult<()> {
+        match self.thread {
+            Some(ref thread) => threads::set_thread_priority(thread, priority),
+            None => Err(HalError(INCOMPATIBLE_STATE)),
+        }
+    }
+
+    fn schedule(&mut self, delay: Duration, period: Option<u64>) -> HalResult<()> {
+        let mut alarm = self.shared.alarm.lock().unwrap();
+
+        alarm.expiration = fpga::get_time_us()? + delay.as_micros() as u64;
+        alarm.period = period;
+        hal_call!(HAL_UpdateNotifierAlarm(self.shared.handle, alarm.expiration))
+    }
+
+    /// Runs the callback once after `delay`, replacing any previous schedule.
+    pub fn start_single(&mut self, delay: Duration) -> HalResult<()> {
+        self.schedule(delay, None)
+    }
+
+    /// Runs the callback every `period`, starting one period from now.
+    pub fn start_periodic(&mut self, period: Duration) -> HalResult<()> {
+        self.schedule(period, Some(period.as_micros() as u64))
+    }
+
+    /// Cancels any scheduled callbacks, one already running is allowed to finish.
+    pub fn stop(&mut self) -> HalResult<()> {
+        let mut alarm = self.shared.alarm.lock().unwrap();
+
+        alarm.period = None;
+        hal_call!(HAL_CancelNotifierAlarm(self.shared.handle))
+    }
+}
+
+impl Drop for Notifier {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_StopNotifier(self.shared.handle));
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        let _ = hal_call!(HAL_CleanNotifier(self.shared.handle));
+    }
+}
\ No newline at end of file
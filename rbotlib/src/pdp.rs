@@ -0,0 +1,131 @@
+use rbothal::*;
+
+pub const NUM_PDP_CHANNELS: usize = 16;
+
+#[derive(Debug)]
+pub struct PowerDistributionPanel {
+    handle: HAL_PDPHandle,
+    module: i32,
+}
+
+impl PowerDistributionPanel {
+    pub fn new(module: i32) -> HalResult<PowerDistributionPanel> {
+        if unsafe { HAL_CheckPDPModule(module) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        Ok(PowerDistributionPanel {
+            handle: hal_call!(HAL_InitializePDP(module))?,
+            module,
+        })
+    }
+
+    pub fn get_module(&self) -> i32 {
+        self.module
+    }
+
+    /// Gets the temperature in degrees Celsius.
+    pub fn get_temperature(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPDPTemperature(self.handle))
+    }
+
+    pub fn get_voltage(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPDPVoltage(self.handle))
+    }
+
+    pub fn get_current(&self, channel: i32) -> HalResult<f64> {
+        if unsafe { HAL_CheckPDPChannel(channel) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        hal_call!(HAL_GetPDPChannelCurrent(self.handle, channel))
+    }
+
+    /// Reads the current of every channel at once, indexed by channel.
+    pub fn get_all_currents(&self) -> HalResult<[f64; NUM_PDP_CHANNELS]> {
+        let mut currents = [0.0; NUM_PDP_CHANNELS];
+
+        hal_call!(HAL_GetPDPAllChannelCurrents(self.handle, currents.as_mut_ptr()))?;
+        Ok(currents)
+    }
+
+    pub fn get_total_current(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPDPTotalCurrent(self.handle))
+    }
+
+    /// Gets the total power in watts.
+    pub fn get_total_power(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPDPTotalPower(self.handle))
+    }
+
+    /// Gets the energy used since the last reset in joules.
+    pub fn get_total_energy(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPDPTotalEnergy(self.handle))
+    }
+
+    pub fn reset_total_energy(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ResetPDPTotalEnergy(self.handle))
+    }
+
+    pub fn clear_sticky_faults(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ClearPDPStickyFaults(self.handle))
+    }
+}
+
+impl Drop for PowerDistributionPanel {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_CleanPDP(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn invalid_module_is_rejected() {
+        assert_eq!(PowerDistributionPanel::new(-1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(PowerDistributionPanel::new(hal_stub::NUM_PDP_MODULES).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        let pdp = PowerDistributionPanel::new(0).unwrap();
+
+        assert_eq!(pdp.get_current(-1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(pdp.get_current(NUM_PDP_CHANNELS as i32).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn currents_are_indexed_by_channel() {
+        let pdp = PowerDistributionPanel::new(0).unwrap();
+        let mut currents = [0.0; NUM_PDP_CHANNELS];
+        for (channel, current) in currents.iter_mut().enumerate() {
+            *current = channel as f64 * 1.5;
+        }
+        hal_stub::set_pdp(pdp.handle, currents, 0.0);
+
+        assert_eq!(pdp.get_all_currents().unwrap(), currents);
+        assert_eq!(pdp.get_current(15).unwrap(), 22.5);
+    }
+
+    #[test]
+    fn energy_resets() {
+        let mut pdp = PowerDistributionPanel::new(1).unwrap();
+        hal_stub::set_pdp(pdp.handle, [0.0; NUM_PDP_CHANNELS], 1200.0);
+
+        assert_eq!(pdp.get_total_energy().unwrap(), 1200.0);
+        pdp.reset_total_energy().unwrap();
+        assert_eq!(pdp.get_total_energy().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn drop_cleans_up() {
+        let handle = PowerDistributionPanel::new(2).unwrap().handle;
+
+        assert!(hal_stub::pdp(handle).cleaned);
+    }
+}
\ No newline at end of file
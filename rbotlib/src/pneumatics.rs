@@ -0,0 +1,322 @@
+use rbothal::*;
+
+pub fn get_all_solenoids(module: i32) -> HalResult<u8> {
+    Ok(hal_call!(HAL_GetAllSolenoids(module))? as u8)
+}
+
+/// Gets a bitmask of the solenoids disabled by the PCM because of a short.
+pub fn get_solenoid_blacklist(module: i32) -> HalResult<u8> {
+    Ok(hal_call!(HAL_GetPCMSolenoidBlackList(module))? as u8)
+}
+
+pub fn get_solenoid_voltage_fault(module: i32) -> HalResult<bool> {
+    Ok(hal_call!(HAL_GetPCMSolenoidVoltageFault(module))? != 0)
+}
+
+pub fn get_solenoid_voltage_sticky_fault(module: i32) -> HalResult<bool> {
+    Ok(hal_call!(HAL_GetPCMSolenoidVoltageStickyFault(module))? != 0)
+}
+
+/// Clears the sticky faults of both the compressor and the solenoids on a PCM.
+pub fn clear_all_sticky_faults(module: i32) -> HalResult<()> {
+    hal_call!(HAL_ClearAllPCMStickyFaults(module))
+}
+
+#[derive(Debug)]
+pub struct Compressor {
+    handle: HAL_CompressorHandle,
+    module: i32,
+}
+
+impl Compressor {
+    pub fn new(module: i32) -> HalResult<Compressor> {
+        if unsafe { HAL_CheckCompressorModule(module) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        let mut compressor = Compressor {
+            handle: hal_call!(HAL_InitializeCompressor(module))?,
+            module,
+        };
+
+        compressor.set_closed_loop_control(true)?;
+        Ok(compressor)
+    }
+
+    pub fn get_module(&self) -> i32 {
+        self.module
+    }
+
+    pub fn start(&mut self) -> HalResult<()> {
+        self.set_closed_loop_control(true)
+    }
+
+    pub fn stop(&mut self) -> HalResult<()> {
+        self.set_closed_loop_control(false)
+    }
+
+    pub fn enabled(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressor(self.handle))? != 0)
+    }
+
+    /// When enabled the PCM runs the compressor until the pressure switch reports full.
+    pub fn set_closed_loop_control(&mut self, on: bool) -> HalResult<()> {
+        hal_call!(HAL_SetCompressorClosedLoopControl(self.handle, on as HAL_Bool))
+    }
+
+    pub fn get_closed_loop_control(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorClosedLoopControl(self.handle))? != 0)
+    }
+
+    /// Returns true when the pressure is low enough that the switch is closed.
+    pub fn get_pressure_switch_value(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorPressureSwitch(self.handle))? != 0)
+    }
+
+    pub fn get_current(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetCompressorCurrent(self.handle))
+    }
+
+    pub fn get_current_too_high_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorCurrentTooHighFault(self.handle))? != 0)
+    }
+
+    pub fn get_current_too_high_sticky_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorCurrentTooHighStickyFault(self.handle))? != 0)
+    }
+
+    pub fn get_shorted_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorShortedFault(self.handle))? != 0)
+    }
+
+    pub fn get_shorted_sticky_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorShortedStickyFault(self.handle))? != 0)
+    }
+
+    pub fn get_not_connected_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorNotConnectedFault(self.handle))? != 0)
+    }
+
+    pub fn get_not_connected_sticky_fault(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetCompressorNotConnectedStickyFault(self.handle))? != 0)
+    }
+
+    pub fn clear_all_sticky_faults(&mut self) -> HalResult<()> {
+        clear_all_sticky_faults(self.module)
+    }
+}
+
+#[derive(Debug)]
+pub struct Solenoid {
+    handle: HAL_SolenoidHandle,
+    module: i32,
+    channel: i32,
+}
+
+impl Solenoid {
+    pub fn new(module: i32, channel: i32) -> HalResult<Solenoid> {
+        if unsafe { HAL_CheckSolenoidModule(module) } == 0 || unsafe { HAL_CheckSolenoidChannel(channel) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        Ok(Solenoid {
+            handle: hal_call!(HAL_InitializeSolenoidPort(HAL_GetPortWithModule(module, channel)))?,
+            module,
+            channel,
+        })
+    }
+
+    pub fn get_module(&self) -> i32 {
+        self.module
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub fn set(&mut self, on: bool) -> HalResult<()> {
+        hal_call!(HAL_SetSolenoid(self.handle, on as HAL_Bool))
+    }
+
+    pub fn get(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetSolenoid(self.handle))? != 0)
+    }
+
+    pub fn is_blacklisted(&self) -> HalResult<bool> {
+        Ok(get_solenoid_blacklist(self.module)? & (1 << self.channel) != 0)
+    }
+
+    /// Sets the length of the pulse fired by `start_pulse`, `duration` is in seconds.
+    pub fn set_pulse_duration(&mut self, duration: f64) -> HalResult<()> {
+        hal_call!(HAL_SetOneShotDuration(self.handle, (duration * 1e3) as i32))
+    }
+
+    /// Turns the solenoid on for the pulse duration, timed by the PCM.
+    pub fn start_pulse(&mut self) -> HalResult<()> {
+        hal_call!(HAL_FireOneShot(self.handle))
+    }
+}
+
+impl Drop for Solenoid {
+    fn drop(&mut self) {
+        unsafe {
+            HAL_FreeSolenoidPort(self.handle);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DoubleSolenoidValue {
+    Off,
+    Forward,
+    Reverse,
+}
+
+#[derive(Debug)]
+pub struct DoubleSolenoid {
+    forward: Solenoid,
+    reverse: Solenoid,
+}
+
+impl DoubleSolenoid {
+    pub fn new(module: i32, forward_channel: i32, reverse_channel: i32) -> HalResult<DoubleSolenoid> {
+        let mut solenoid = DoubleSolenoid {
+            forward: Solenoid::new(module, forward_channel)?,
+            reverse: Solenoid::new(module, reverse_channel)?,
+        };
+
+        solenoid.set(DoubleSolenoidValue::Off)?;
+        Ok(solenoid)
+    }
+
+    pub fn get_module(&self) -> i32 {
+        self.forward.get_module()
+    }
+
+    pub fn get_forward_channel(&self) -> i32 {
+        self.forward.get_channel()
+    }
+
+    pub fn get_reverse_channel(&self) -> i32 {
+        self.reverse.get_channel()
+    }
+
+    /// Always turns one side off before the other side on, so both are never on together.
+    pub fn set(&mut self, value: DoubleSolenoidValue) -> HalResult<()> {
+        match value {
+            DoubleSolenoidValue::Off => {
+                self.forward.set(false)?;
+                self.reverse.set(false)
+            }
+            DoubleSolenoidValue::Forward => {
+                self.reverse.set(false)?;
+                self.forward.set(true)
+            }
+            DoubleSolenoidValue::Reverse => {
+                self.forward.set(false)?;
+                self.reverse.set(true)
+            }
+        }
+    }
+
+    pub fn get(&self) -> HalResult<DoubleSolenoidValue> {
+        if self.forward.get()? {
+            Ok(DoubleSolenoidValue::Forward)
+        } else if self.reverse.get()? {
+            Ok(DoubleSolenoidValue::Reverse)
+        } else {
+            Ok(DoubleSolenoidValue::Off)
+        }
+    }
+
+    pub fn toggle(&mut self) -> HalResult<()> {
+        match self.get()? {
+            DoubleSolenoidValue::Forward => self.set(DoubleSolenoidValue::Reverse),
+            DoubleSolenoidValue::Reverse => self.set(DoubleSolenoidValue::Forward),
+            DoubleSolenoidValue::Off => Ok(()),
+        }
+    }
+
+    pub fn is_forward_blacklisted(&self) -> HalResult<bool> {
+        self.forward.is_blacklisted()
+    }
+
+    pub fn is_reverse_blacklisted(&self) -> HalResult<bool> {
+        self.reverse.is_blacklisted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    const FORWARD: i32 = 0;
+    const REVERSE: i32 = 1;
+
+    #[test]
+    fn invalid_port_is_rejected() {
+        assert_eq!(Solenoid::new(hal_stub::NUM_SOLENOID_MODULES, 0).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(Solenoid::new(0, hal_stub::NUM_SOLENOID_CHANNELS).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        assert_eq!(DoubleSolenoid::new(0, 2, -1).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+
+        // The forward side opened before the reverse side failed is released again.
+        assert!(Solenoid::new(0, 2).is_ok());
+    }
+
+    #[test]
+    fn channel_in_use_is_rejected() {
+        let _solenoid = Solenoid::new(1, 3).unwrap();
+
+        assert_eq!(DoubleSolenoid::new(1, 4, 3).unwrap_err().0, RESOURCE_IS_ALLOCATED);
+        assert!(Solenoid::new(1, 4).is_ok());
+    }
+
+    #[test]
+    fn double_solenoid_turns_one_side_off_before_the_other_on() {
+        let mut solenoid = DoubleSolenoid::new(0, FORWARD, REVERSE).unwrap();
+        assert_eq!(hal_stub::take_solenoid_writes(), [(FORWARD, false), (REVERSE, false)]);
+
+        solenoid.set(DoubleSolenoidValue::Forward).unwrap();
+        assert_eq!(hal_stub::take_solenoid_writes(), [(REVERSE, false), (FORWARD, true)]);
+        assert_eq!(solenoid.get().unwrap(), DoubleSolenoidValue::Forward);
+
+        solenoid.set(DoubleSolenoidValue::Reverse).unwrap();
+        assert_eq!(hal_stub::take_solenoid_writes(), [(FORWARD, false), (REVERSE, true)]);
+        assert_eq!(solenoid.get().unwrap(), DoubleSolenoidValue::Reverse);
+
+        solenoid.set(DoubleSolenoidValue::Off).unwrap();
+        assert_eq!(hal_stub::take_solenoid_writes(), [(FORWARD, false), (REVERSE, false)]);
+        assert_eq!(solenoid.get().unwrap(), DoubleSolenoidValue::Off);
+    }
+
+    #[test]
+    fn toggle_never_drives_both_sides() {
+        let mut solenoid = DoubleSolenoid::new(0, FORWARD, REVERSE).unwrap();
+
+        solenoid.toggle().unwrap();
+        assert_eq!(solenoid.get().unwrap(), DoubleSolenoidValue::Off);
+
+        solenoid.set(DoubleSolenoidValue::Forward).unwrap();
+        for _ in 0..4 {
+            solenoid.toggle().unwrap();
+        }
+
+        let mut on = [false; 2];
+        for (channel, value) in hal_stub::take_solenoid_writes() {
+            on[channel as usize] = value;
+            assert!(!(on[0] && on[1]));
+        }
+        assert_eq!(solenoid.get().unwrap(), DoubleSolenoidValue::Forward);
+    }
+
+    #[test]
+    fn drop_frees_both_sides() {
+        let solenoid = DoubleSolenoid::new(2, FORWARD, REVERSE).unwrap();
+        let handles = (solenoid.forward.handle, solenoid.reverse.handle);
+
+        drop(solenoid);
+        assert!(hal_stub::solenoid(handles.0).freed && hal_stub::solenoid(handles.1).freed);
+        assert_eq!(hal_stub::solenoid(handles.1).module, 2);
+    }
+}
\ No newline at end of file
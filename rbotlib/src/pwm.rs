@@ -0,0 +1,298 @@
+use rbothal::*;
+
+use crate::speed_controller::{forward_speed_controller, SpeedController};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PeriodMultiplier {
+    K1X,
+    K2X,
+    K4X,
+}
+
+impl PeriodMultiplier {
+    fn squelch_mask(self) -> i32 {
+        match self {
+            PeriodMultiplier::K1X => 3,
+            PeriodMultiplier::K2X => 1,
+            PeriodMultiplier::K4X => 0,
+        }
+    }
+}
+
+/// Pulse widths in milliseconds passed to `HAL_SetPWMConfig`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PwmBounds {
+    pub max: f64,
+    pub deadband_max: f64,
+    pub center: f64,
+    pub deadband_min: f64,
+    pub min: f64,
+}
+
+impl PwmBounds {
+    pub const TALON_SRX: PwmBounds = PwmBounds {
+        max: 2.004,
+        deadband_max: 1.52,
+        center: 1.50,
+        deadband_min: 1.48,
+        min: 0.997,
+    };
+
+    pub const VICTOR_SPX: PwmBounds = PwmBounds {
+        max: 2.004,
+        deadband_max: 1.52,
+        center: 1.50,
+        deadband_min: 1.48,
+        min: 0.997,
+    };
+
+    pub const SPARK: PwmBounds = PwmBounds {
+        max: 2.003,
+        deadband_max: 1.55,
+        center: 1.50,
+        deadband_min: 1.46,
+        min: 0.999,
+    };
+
+    pub const SPARK_MAX: PwmBounds = PwmBounds {
+        max: 2.003,
+        deadband_max: 1.55,
+        center: 1.50,
+        deadband_min: 1.46,
+        min: 0.999,
+    };
+}
+
+#[derive(Debug)]
+pub struct Pwm {
+    handle: HAL_DigitalHandle,
+    channel: i32,
+}
+
+impl Pwm {
+    pub fn new(channel: i32) -> HalResult<Pwm> {
+        let handle = hal_call!(HAL_InitializePWMPort(HAL_GetPort(channel)))?;
+        let pwm = Pwm { handle, channel };
+
+        hal_call!(HAL_SetPWMDisabled(handle))?;
+        hal_call!(HAL_SetPWMEliminateDeadband(handle, 0))?;
+
+        Ok(pwm)
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub fn set_raw(&mut self, value: u16) -> HalResult<()> {
+        hal_call!(HAL_SetPWMRaw(self.handle, i32::from(value)))
+    }
+
+    pub fn get_raw(&self) -> HalResult<u16> {
+        Ok(hal_call!(HAL_GetPWMRaw(self.handle))? as u16)
+    }
+
+    pub fn set_position(&mut self, position: f64) -> HalResult<()> {
+        hal_call!(HAL_SetPWMPosition(self.handle, position))
+    }
+
+    pub fn get_position(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPWMPosition(self.handle))
+    }
+
+    pub fn set_speed(&mut self, speed: f64) -> HalResult<()> {
+        hal_call!(HAL_SetPWMSpeed(self.handle, speed))
+    }
+
+    pub fn get_speed(&self) -> HalResult<f64> {
+        hal_call!(HAL_GetPWMSpeed(self.handle))
+    }
+
+    pub fn set_disabled(&mut self) -> HalResult<()> {
+        hal_call!(HAL_SetPWMDisabled(self.handle))
+    }
+
+    pub fn set_period_multiplier(&mut self, multiplier: PeriodMultiplier) -> HalResult<()> {
+        hal_call!(HAL_SetPWMPeriodScale(self.handle, multiplier.squelch_mask()))
+    }
+
+    pub fn set_zero_latch(&mut self) -> HalResult<()> {
+        hal_call!(HAL_LatchPWMZero(self.handle))
+    }
+
+    pub fn enable_deadband_elimination(&mut self, eliminate: bool) -> HalResult<()> {
+        hal_call!(HAL_SetPWMEliminateDeadband(self.handle, eliminate as HAL_Bool))
+    }
+
+    pub fn get_deadband_elimination(&self) -> HalResult<bool> {
+        Ok(hal_call!(HAL_GetPWMEliminateDeadband(self.handle))? != 0)
+    }
+
+    pub fn set_bounds(&mut self, bounds: PwmBounds) -> HalResult<()> {
+        hal_call!(HAL_SetPWMConfig(
+            self.handle,
+            bounds.max,
+            bounds.deadband_max,
+            bounds.center,
+            bounds.deadband_min,
+            bounds.min,
+        ))
+    }
+}
+
+impl Drop for Pwm {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_SetPWMDisabled(self.handle));
+        let _ = hal_call!(HAL_FreePWMPort(self.handle));
+    }
+}
+
+#[derive(Debug)]
+pub struct PwmSpeedController {
+    pwm: Pwm,
+    inverted: bool,
+}
+
+impl PwmSpeedController {
+    pub fn new(channel: i32, bounds: PwmBounds) -> HalResult<PwmSpeedController> {
+        let mut pwm = Pwm::new(channel)?;
+
+        pwm.set_bounds(bounds)?;
+        pwm.set_period_multiplier(PeriodMultiplier::K1X)?;
+        pwm.set_speed(0.0)?;
+        pwm.set_zero_latch()?;
+
+        Ok(PwmSpeedController {
+            pwm,
+            inverted: false,
+        })
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.pwm.get_channel()
+    }
+
+    pub fn enable_deadband_elimination(&mut self, eliminate: bool) -> HalResult<()> {
+        self.pwm.enable_deadband_elimination(eliminate)
+    }
+}
+
+impl SpeedController for PwmSpeedController {
+    fn set(&mut self, speed: f64) -> HalResult<()> {
+        self.pwm.set_speed(if self.inverted { -speed } else { speed })
+    }
+
+    fn get(&self) -> HalResult<f64> {
+        let speed = self.pwm.get_speed()?;
+
+        Ok(if self.inverted { -speed } else { speed })
+    }
+
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    fn get_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    fn disable(&mut self) -> HalResult<()> {
+        self.pwm.set_disabled()
+    }
+}
+
+macro_rules! pwm_speed_controller {
+    ($name:ident, $bounds:expr) => {
+        #[derive(Debug)]
+        pub struct $name(PwmSpeedController);
+
+        impl $name {
+            pub fn new(channel: i32) -> HalResult<$name> {
+                Ok($name(PwmSpeedController::new(channel, $bounds)?))
+            }
+        }
+
+        forward_speed_controller!($name, PwmSpeedController);
+    };
+}
+
+pwm_speed_controller!(PwmTalonSrx, PwmBounds::TALON_SRX);
+pwm_speed_controller!(PwmVictorSpx, PwmBounds::VICTOR_SPX);
+pwm_speed_controller!(Spark, PwmBounds::SPARK);
+pwm_speed_controller!(PwmSparkMax, PwmBounds::SPARK_MAX);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn vendor_bounds_are_applied() {
+        let cases = [
+            (PwmTalonSrx::new(0).unwrap().pwm.handle, PwmBounds::TALON_SRX),
+            (PwmVictorSpx::new(1).unwrap().pwm.handle, PwmBounds::VICTOR_SPX),
+            (Spark::new(2).unwrap().pwm.handle, PwmBounds::SPARK),
+            (PwmSparkMax::new(3).unwrap().pwm.handle, PwmBounds::SPARK_MAX),
+        ];
+
+        for (handle, bounds) in cases.iter() {
+            let state = hal_stub::pwm(*handle);
+
+            assert_eq!(state.config, Some(*bounds));
+            assert_eq!(state.period_scale, 3);
+            assert!(state.zero_latched);
+            assert!(!state.eliminate_deadband);
+            assert!(state.freed);
+        }
+    }
+
+    #[test]
+    fn inversion_negates_speed() {
+        let mut talon = PwmTalonSrx::new(4).unwrap();
+
+        talon.set(0.5).unwrap();
+        assert_eq!(hal_stub::pwm(talon.pwm.handle).speed, 0.5);
+
+        talon.set_inverted(true);
+        talon.set(0.5).unwrap();
+        assert_eq!(hal_stub::pwm(talon.pwm.handle).speed, -0.5);
+        assert_eq!(talon.get().unwrap(), 0.5);
+    }
+
+    #[test]
+    fn deadband_elimination_is_forwarded() {
+        let mut spark = Spark::new(5).unwrap();
+
+        spark.enable_deadband_elimination(true).unwrap();
+        assert!(hal_stub::pwm(spark.pwm.handle).eliminate_deadband);
+    }
+
+    #[test]
+    fn disable_stops_signal() {
+        let mut victor = PwmVictorSpx::new(6).unwrap();
+
+        victor.set(1.0).unwrap();
+        victor.stop_motor().unwrap();
+        assert!(hal_stub::pwm(victor.pwm.handle).disabled);
+    }
+
+    #[test]
+    fn drop_frees_port() {
+        let handle = Pwm::new(7).unwrap().handle;
+
+        assert!(hal_stub::pwm(handle).freed);
+        assert!(Pwm::new(7).is_ok());
+    }
+
+    #[test]
+    fn channel_in_use_is_rejected() {
+        let _pwm = Pwm::new(8).unwrap();
+
+        assert!(Pwm::new(8).is_err());
+    }
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        assert!(Pwm::new(hal_stub::NUM_PWM_CHANNELS).is_err());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RelayDirection {
+    Both,
+    ForwardOnly,
+    ReverseOnly,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RelayValue {
+    Off,
+    /// Both outputs on, or the allowed output on when restricted to one direction.
+    On,
+    Forward,
+    Reverse,
+}
+
+#[derive(Debug)]
+pub struct Relay {
+    channel: i32,
+    direction: RelayDirection,
+    forward: Option<HAL_RelayHandle>,
+    reverse: Option<HAL_RelayHandle>,
+}
+
+impl Relay {
+    pub fn new(channel: i32, direction: RelayDirection) -> HalResult<Relay> {
+        if unsafe { HAL_CheckRelayChannel(channel) } == 0 {
+            return Err(HalError(RESOURCE_OUT_OF_RANGE));
+        }
+
+        let mut relay = Relay {
+            channel,
+            direction,
+            forward: None,
+            reverse: None,
+        };
+
+        relay.allocate()?;
+        relay.set(RelayValue::Off)?;
+        Ok(relay)
+    }
+
+    fn allocate(&mut self) -> HalResult<()> {
+        if self.direction != RelayDirection::ReverseOnly {
+            self.forward = Some(hal_call!(HAL_InitializeRelayPort(HAL_GetPort(self.channel), 1))?);
+        }
+
+        if self.direction != RelayDirection::ForwardOnly {
+            self.reverse = Some(hal_call!(HAL_InitializeRelayPort(HAL_GetPort(self.channel), 0))?);
+        }
+
+        Ok(())
+    }
+
+    fn free(&mut self) {
+        for handle in self.forward.take().into_iter().chain(self.reverse.take()) {
+            unsafe {
+                HAL_FreeRelayPort(handle);
+            }
+        }
+    }
+
+    pub fn get_channel(&self) -> i32 {
+        self.channel
+    }
+
+    pub fn get_direction(&self) -> RelayDirection {
+        self.direction
+    }
+
+    /// Reallocates the relay for a new direction, turning it off.
+    pub fn set_direction(&mut self, direction: RelayDirection) -> HalResult<()> {
+        if self.direction == direction {
+            return Ok(());
+        }
+
+        self.free();
+        self.direction = direction;
+        self.allocate()?;
+        self.set(RelayValue::Off)
+    }
+
+    pub fn set(&mut self, value: RelayValue) -> HalResult<()> {
+        let (forward, reverse) = match (value, self.direction) {
+            (RelayValue::Off, _) => (false, false),
+            (RelayValue::On, _) => (true, true),
+            (RelayValue::Forward, RelayDirection::ReverseOnly) => return Err(HalError(INCOMPATIBLE_STATE)),
+            (RelayValue::Forward, _) => (true, false),
+            (RelayValue::Reverse, RelayDirection::ForwardOnly) => return Err(HalError(INCOMPATIBLE_STATE)),
+            (RelayValue::Reverse, _) => (false, true),
+        };
+
+        if let Some(handle) = self.forward {
+            hal_call!(HAL_SetRelay(handle, forward as HAL_Bool))?;
+        }
+
+        if let Some(handle) = self.reverse {
+            hal_call!(HAL_SetRelay(handle, reverse as HAL_Bool))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self) -> HalResult<RelayValue> {
+        let forward = match self.forward {
+            Some(handle) => hal_call!(HAL_GetRelay(handle))? != 0,
+            None => false,
+        };
+        let reverse = match self.reverse {
+            Some(handle) => hal_call!(HAL_GetRelay(handle))? != 0,
+            None => false,
+        };
+
+        Ok(match (self.direction, forward, reverse) {
+            (RelayDirection::Both, true, true) => RelayValue::On,
+            (RelayDirection::Both, true, false) => RelayValue::Forward,
+            (RelayDirection::Both, false, true) => RelayValue::Reverse,
+            (_, false, false) => RelayValue::Off,
+            _ => RelayValue::On,
+        })
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        let _ = self.set(RelayValue::Off);
+        self.free();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    fn outputs(relay: &Relay) -> (Option<bool>, Option<bool>) {
+        (
+            relay.forward.map(|handle| hal_stub::relay(handle).on),
+            relay.reverse.map(|handle| hal_stub::relay(handle).on),
+        )
+    }
+
+    #[test]
+    fn invalid_channel_is_rejected() {
+        for channel in [-1, hal_stub::NUM_RELAY_CHANNELS].iter() {
+            assert_eq!(Relay::new(*channel, RelayDirection::Both).unwrap_err().0, RESOURCE_OUT_OF_RANGE);
+        }
+    }
+
+    #[test]
+    fn both_directions_drive_each_output() {
+        let mut relay = Relay::new(0, RelayDirection::Both).unwrap();
+        assert_eq!(outputs(&relay), (Some(false), Some(false)));
+
+        let cases = [
+            (RelayValue::Forward, (Some(true), Some(false))),
+            (RelayValue::Reverse, (Some(false), Some(true))),
+            (RelayValue::On, (Some(true), Some(true))),
+            (RelayValue::Off, (Some(false), Some(false))),
+        ];
+
+        for (value, expected) in cases.iter() {
+            relay.set(*value).unwrap();
+            assert_eq!(outputs(&relay), *expected);
+            assert_eq!(relay.get().unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn single_direction_rejects_the_other_side() {
+        let mut forward = Relay::new(1, RelayDirection::ForwardOnly).unwrap();
+        assert_eq!(outputs(&forward), (Some(false), None));
+        assert_eq!(forward.set(RelayValue::Reverse).unwrap_err().0, INCOMPATIBLE_STATE);
+        forward.set(RelayValue::Forward).unwrap();
+        assert_eq!(forward.get().unwrap(), RelayValue::On);
+
+        // The unused reverse output of the same channel is still free.
+        let mut reverse = Relay::new(1, RelayDirection::ReverseOnly).unwrap();
+        assert_eq!(outputs(&reverse), (None, Some(false)));
+        assert_eq!(reverse.set(RelayValue::Forward).unwrap_err().0, INCOMPATIBLE_STATE);
+        reverse.set(RelayValue::On).unwrap();
+        assert_eq!(outputs(&reverse), (None, Some(true)));
+        assert_eq!(reverse.get().unwrap(), RelayValue::On);
+
+        assert!(Relay::new(1, RelayDirection::Both).is_err());
+    }
+
+    #[test]
+    fn set_direction_reallocates_and_turns_off() {
+        let mut relay = Relay::new(2, RelayDirection::Both).unwrap();
+        relay.set(RelayValue::On).unwrap();
+        let reverse = relay.reverse.unwrap();
+        assert_eq!(hal_stub::relay(reverse).channel, 2);
+        assert!(!hal_stub::relay(reverse).forward);
+
+        relay.set_direction(RelayDirection::ForwardOnly).unwrap();
+        assert!(hal_stub::relay(reverse).freed);
+        assert_eq!(outputs(&relay), (Some(false), None));
+        assert_eq!(relay.get_direction(), RelayDirection::ForwardOnly);
+    }
+
+    #[test]
+    fn drop_turns_off_and_frees() {
+        let mut relay = Relay::new(3, RelayDirection::Both).unwrap();
+        relay.set(RelayValue::On).unwrap();
+        let handles = [relay.forward.unwrap(), relay.reverse.unwrap()];
+
+        drop(relay);
+        for handle in handles.iter() {
+            let state = hal_stub::relay(*handle);
+            assert!(!state.on && state.freed);
+        }
+    }
+}
\ No newline at end of file
@@ -14,4 +14,166 @@ pub fn get_battery_voltage() -> HalResult<f64> {
 
 pub fn get_battery_current() -> HalResult<f64> {
     hal_call!(HAL_GetVinCurrent())
+}
+
+/// One of the regulated user rails on the roboRIO, which shut off when overloaded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PowerRail {
+    User6V,
+    User5V,
+    User3V3,
+}
+
+impl PowerRail {
+    pub const ALL: [PowerRail; 3] = [PowerRail::User6V, PowerRail::User5V, PowerRail::User3V3];
+
+    pub fn get_voltage(self) -> HalResult<f64> {
+        match self {
+            PowerRail::User6V => hal_call!(HAL_GetUserVoltage6V()),
+            PowerRail::User5V => hal_call!(HAL_GetUserVoltage5V()),
+            PowerRail::User3V3 => hal_call!(HAL_GetUserVoltage3V3()),
+        }
+    }
+
+    pub fn get_current(self) -> HalResult<f64> {
+        match self {
+            PowerRail::User6V => hal_call!(HAL_GetUserCurrent6V()),
+            PowerRail::User5V => hal_call!(HAL_GetUserCurrent5V()),
+            PowerRail::User3V3 => hal_call!(HAL_GetUserCurrent3V3()),
+        }
+    }
+
+    /// Whether the rail is enabled, it turns off on a brownout or a short.
+    pub fn is_active(self) -> HalResult<bool> {
+        let active = match self {
+            PowerRail::User6V => hal_call!(HAL_GetUserActive6V())?,
+            PowerRail::User5V => hal_call!(HAL_GetUserActive5V())?,
+            PowerRail::User3V3 => hal_call!(HAL_GetUserActive3V3())?,
+        };
+
+        Ok(active != 0)
+    }
+
+    /// Gets the number of overcurrent faults since the roboRIO booted.
+    pub fn get_current_faults(self) -> HalResult<i32> {
+        match self {
+            PowerRail::User6V => hal_call!(HAL_GetUserCurrentFaults6V()),
+            PowerRail::User5V => hal_call!(HAL_GetUserCurrentFaults5V()),
+            PowerRail::User3V3 => hal_call!(HAL_GetUserCurrentFaults3V3()),
+        }
+    }
+
+    pub fn get_status(self) -> HalResult<RailStatus> {
+        Ok(RailStatus {
+            voltage: self.get_voltage()?,
+            current: self.get_current()?,
+            active: self.is_active()?,
+            current_faults: self.get_current_faults()?,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RailStatus {
+    pub voltage: f64,
+    pub current: f64,
+    pub active: bool,
+    pub current_faults: i32,
+}
+
+/// The status of all three user rails, read one after another.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PowerRails {
+    pub rail_6v: RailStatus,
+    pub rail_5v: RailStatus,
+    pub rail_3v3: RailStatus,
+}
+
+impl PowerRails {
+    pub fn read() -> HalResult<PowerRails> {
+        Ok(PowerRails {
+            rail_6v: PowerRail::User6V.get_status()?,
+            rail_5v: PowerRail::User5V.get_status()?,
+            rail_3v3: PowerRail::User3V3.get_status()?,
+        })
+    }
+
+    pub fn get_rail(&self, rail: PowerRail) -> RailStatus {
+        match rail {
+            PowerRail::User6V => self.rail_6v,
+            PowerRail::User5V => self.rail_5v,
+            PowerRail::User3V3 => self.rail_3v3,
+        }
+    }
+
+    /// Gets the rails that are off or have faulted since boot.
+    pub fn get_unhealthy_rails(&self) -> Vec<PowerRail> {
+        PowerRail::ALL
+            .iter()
+            .cloned()
+            .filter(|&rail| {
+                let status = self.get_rail(rail);
+                !status.active || status.current_faults > 0
+            })
+            .collect()
+    }
+}
+
+/// Battery and rail readings taken together, meant for logging every loop.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PowerSnapshot {
+    pub battery_voltage: f64,
+    pub battery_current: f64,
+    pub browned_out: bool,
+    pub rails: PowerRails,
+}
+
+pub fn get_power_snapshot() -> HalResult<PowerSnapshot> {
+    Ok(PowerSnapshot {
+        battery_voltage: get_battery_voltage()?,
+        battery_current: get_battery_current()?,
+        browned_out: is_browned_out()?,
+        rails: PowerRails::read()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn snapshot_reads_battery_and_rails() {
+        hal_stub::set_battery(12.5, 40.0, false);
+        hal_stub::set_power_rail(0, 6.1, 0.5, true, 0);
+        hal_stub::set_power_rail(1, 4.2, 2.5, false, 3);
+        hal_stub::set_power_rail(2, 3.3, 0.1, true, 0);
+
+        let snapshot = get_power_snapshot().unwrap();
+        assert_eq!(snapshot.battery_voltage, 12.5);
+        assert_eq!(snapshot.battery_current, 40.0);
+        assert!(!snapshot.browned_out);
+
+        let rail_5v = RailStatus {
+            voltage: 4.2,
+            current: 2.5,
+            active: false,
+            current_faults: 3,
+        };
+        assert_eq!(snapshot.rails.rail_6v.voltage, 6.1);
+        assert_eq!(snapshot.rails.rail_5v, rail_5v);
+        assert_eq!(snapshot.rails.get_rail(PowerRail::User3V3).current, 0.1);
+        assert_eq!(snapshot.rails.get_unhealthy_rails(), vec![PowerRail::User5V]);
+    }
+
+    #[test]
+    fn faulted_rail_is_unhealthy_while_active() {
+        hal_stub::set_power_rail(0, 6.0, 0.0, true, 1);
+        hal_stub::set_power_rail(1, 5.0, 0.0, true, 0);
+        hal_stub::set_power_rail(2, 3.3, 0.0, true, 0);
+
+        assert_eq!(PowerRails::read().unwrap().get_unhealthy_rails(), vec![PowerRail::User6V]);
+        assert!(PowerRail::User5V.is_active().unwrap());
+        assert_eq!(PowerRail::User6V.get_current_faults().unwrap(), 1);
+    }
 }
\ No newline at end of file
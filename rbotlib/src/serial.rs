@@ -0,0 +1,382 @@
+use std::ffi::CString;
+use std::io::{self, Read, Write};
+use std::os::raw::c_char;
+use std::time::Duration;
+
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SerialPortId {
+    /// The RS-232 port on the roboRIO.
+    Onboard,
+    /// The UART pins on the MXP.
+    Mxp,
+    /// The top USB host port.
+    Usb1,
+    /// The bottom USB host port.
+    Usb2,
+}
+
+impl From<SerialPortId> for HAL_SerialPort::Type {
+    fn from(port: SerialPortId) -> HAL_SerialPort::Type {
+        match port {
+            SerialPortId::Onboard => HAL_SerialPort::Onboard,
+            SerialPortId::Mxp => HAL_SerialPort::MXP,
+            SerialPortId::Usb1 => HAL_SerialPort::USB1,
+            SerialPortId::Usb2 => HAL_SerialPort::USB2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Parity {
+    None = 0,
+    Odd = 1,
+    Even = 2,
+    /// Parity bit is always 1.
+    Mark = 3,
+    /// Parity bit is always 0.
+    Space = 4,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StopBits {
+    One = 10,
+    OnePointFive = 15,
+    Two = 20,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FlowControl {
+    None = 0,
+    XonXoff = 1,
+    RtsCts = 2,
+    DtrDsr = 3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WriteBufferMode {
+    FlushOnAccess = 1,
+    FlushWhenFull = 2,
+}
+
+#[derive(Clone, Debug)]
+pub struct SerialPortBuilder {
+    port: SerialPortId,
+    device_path: Option<String>,
+    baud_rate: i32,
+    data_bits: i32,
+    parity: Parity,
+    stop_bits: StopBits,
+    flow_control: FlowControl,
+    write_buffer_mode: WriteBufferMode,
+    timeout: Option<Duration>,
+    termination: Option<u8>,
+    read_buffer_size: Option<i32>,
+    write_buffer_size: Option<i32>,
+}
+
+impl SerialPortBuilder {
+    pub fn new(port: SerialPortId) -> SerialPortBuilder {
+        SerialPortBuilder {
+            port,
+            device_path: None,
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            write_buffer_mode: WriteBufferMode::FlushWhenFull,
+            timeout: None,
+            termination: None,
+            read_buffer_size: None,
+            write_buffer_size: None,
+        }
+    }
+
+    /// Opens `path` directly instead of the default device for the port, useful for USB devices
+    /// whose `/dev` name is known.
+    pub fn device_path(mut self, path: &str) -> SerialPortBuilder {
+        self.device_path = Some(path.to_owned());
+        self
+    }
+
+    pub fn baud_rate(mut self, baud_rate: i32) -> SerialPortBuilder {
+        self.baud_rate = baud_rate;
+        self
+    }
+
+    /// Sets the number of data bits, from 5 to 8.
+    pub fn data_bits(mut self, data_bits: i32) -> SerialPortBuilder {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> SerialPortBuilder {
+        self.parity = parity;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> SerialPortBuilder {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn flow_control(mut self, flow_control: FlowControl) -> SerialPortBuilder {
+        self.flow_control = flow_control;
+        self
+    }
+
+    pub fn write_buffer_mode(mut self, mode: WriteBufferMode) -> SerialPortBuilder {
+        self.write_buffer_mode = mode;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> SerialPortBuilder {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Ends reads early when `terminator` is received, such as `b'\n'` for line based protocols.
+    pub fn termination(mut self, terminator: u8) -> SerialPortBuilder {
+        self.termination = Some(terminator);
+        self
+    }
+
+    pub fn read_buffer_size(mut self, size: i32) -> SerialPortBuilder {
+        self.read_buffer_size = Some(size);
+        self
+    }
+
+    pub fn write_buffer_size(mut self, size: i32) -> SerialPortBuilder {
+        self.write_buffer_size = Some(size);
+        self
+    }
+
+    pub fn build(self) -> HalResult<SerialPort> {
+        let handle = match self.device_path {
+            Some(path) => {
+                let path = CString::new(path).map_err(|_| HalError(PARAMETER_OUT_OF_RANGE))?;
+
+                hal_call!(HAL_InitializeSerialPortDirect(self.port.into(), path.as_ptr()))?
+            }
+            None => hal_call!(HAL_InitializeSerialPort(self.port.into()))?,
+        };
+        let mut serial_port = SerialPort { handle, port: self.port };
+
+        hal_call!(HAL_SetSerialBaudRate(handle, self.baud_rate))?;
+        hal_call!(HAL_SetSerialDataBits(handle, self.data_bits))?;
+        hal_call!(HAL_SetSerialParity(handle, self.parity as i32))?;
+        hal_call!(HAL_SetSerialStopBits(handle, self.stop_bits as i32))?;
+        hal_call!(HAL_SetSerialFlowControl(handle, self.flow_control as i32))?;
+        hal_call!(HAL_SetSerialWriteMode(handle, self.write_buffer_mode as i32))?;
+
+        if let Some(timeout) = self.timeout {
+            serial_port.set_timeout(timeout)?;
+        }
+
+        serial_port.set_termination(self.termination)?;
+
+        if let Some(size) = self.read_buffer_size {
+            hal_call!(HAL_SetSerialReadBufferSize(handle, size))?;
+        }
+
+        if let Some(size) = self.write_buffer_size {
+            hal_call!(HAL_SetSerialWriteBufferSize(handle, size))?;
+        }
+
+        Ok(serial_port)
+    }
+}
+
+#[derive(Debug)]
+pub struct SerialPort {
+    handle: HAL_SerialPortHandle,
+    port: SerialPortId,
+}
+
+impl SerialPort {
+    pub fn builder(port: SerialPortId) -> SerialPortBuilder {
+        SerialPortBuilder::new(port)
+    }
+
+    pub fn get_port(&self) -> SerialPortId {
+        self.port
+    }
+
+    /// Sets how long reads wait for data before returning what they have.
+    pub fn set_timeout(&mut self, timeout: Duration) -> HalResult<()> {
+        hal_call!(HAL_SetSerialTimeout(self.handle, timeout.as_secs_f64() * 1000.0))
+    }
+
+    pub fn set_termination(&mut self, terminator: Option<u8>) -> HalResult<()> {
+        match terminator {
+            Some(terminator) => hal_call!(HAL_EnableSerialTermination(self.handle, terminator as c_char)),
+            None => hal_call!(HAL_DisableSerialTermination(self.handle)),
+        }
+    }
+
+    pub fn get_bytes_received(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetSerialBytesReceived(self.handle))
+    }
+
+    /// Discards everything received but not yet read.
+    pub fn clear(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ClearSerial(self.handle))
+    }
+}
+
+fn to_io_error(error: HalError) -> io::Error {
+    io::Error::other(error)
+}
+
+impl Read for SerialPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = hal_call!(HAL_ReadSerial(
+            self.handle,
+            buf.as_mut_ptr() as *mut c_char,
+            buf.len() as i32,
+        ))
+        .map_err(to_io_error)?;
+
+        // Zero bytes would look like end of file to `read_exact` and `read_to_end`.
+        if read == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "serial read timed out"));
+        }
+
+        Ok(read as usize)
+    }
+}
+
+impl Write for SerialPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = hal_call!(HAL_WriteSerial(
+            self.handle,
+            buf.as_ptr() as *const c_char,
+            buf.len() as i32,
+        ))
+        .map_err(to_io_error)?;
+
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        hal_call!(HAL_FlushSerial(self.handle)).map_err(to_io_error)
+    }
+}
+
+impl Drop for SerialPort {
+    fn drop(&mut self) {
+        let _ = hal_call!(HAL_CloseSerial(self.handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    #[test]
+    fn builder_applies_configuration() {
+        let (_master, path) = hal_stub::open_pty();
+        let serial_port = SerialPort::builder(SerialPortId::Usb1)
+            .device_path(&path)
+            .baud_rate(115_200)
+            .data_bits(7)
+            .parity(Parity::Even)
+            .stop_bits(StopBits::Two)
+            .flow_control(FlowControl::RtsCts)
+            .write_buffer_mode(WriteBufferMode::FlushOnAccess)
+            .timeout(Duration::from_millis(250))
+            .termination(b'\n')
+            .read_buffer_size(1024)
+            .write_buffer_size(512)
+            .build()
+            .unwrap();
+        let state = hal_stub::serial(serial_port.handle);
+
+        assert_eq!(state.path, path);
+        assert_eq!(state.baud_rate, 115_200);
+        assert_eq!(state.data_bits, 7);
+        assert_eq!(state.parity, 2);
+        assert_eq!(state.stop_bits, 20);
+        assert_eq!(state.flow_control, 2);
+        assert_eq!(state.write_mode, 1);
+        assert_eq!(state.timeout, 250.0);
+        assert_eq!(state.termination, Some(b'\n' as c_char));
+        assert_eq!(state.read_buffer_size, 1024);
+        assert_eq!(state.write_buffer_size, 512);
+    }
+
+    #[test]
+    fn reads_and_writes_over_pty() {
+        let (mut master, path) = hal_stub::open_pty();
+        let mut serial_port = SerialPort::builder(SerialPortId::Usb1).device_path(&path).build().unwrap();
+
+        master.write_all(b"ping").unwrap();
+        let mut received = [0; 4];
+        serial_port.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"ping");
+
+        serial_port.write_all(b"pong").unwrap();
+        serial_port.flush().unwrap();
+        let mut received = [0; 4];
+        master.read_exact(&mut received).unwrap();
+        assert_eq!(&received, b"pong");
+    }
+
+    #[test]
+    fn read_timeout_is_not_end_of_file() {
+        let (mut master, path) = hal_stub::open_pty();
+        let mut serial_port = SerialPort::builder(SerialPortId::Usb1)
+            .device_path(&path)
+            .timeout(Duration::from_millis(20))
+            .build()
+            .unwrap();
+
+        assert_eq!(serial_port.read(&mut []).unwrap(), 0);
+        assert_eq!(serial_port.read(&mut [0; 4]).unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        master.write_all(b"pi").unwrap();
+        let error = serial_port.read_exact(&mut [0; 4]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn invalid_device_path_is_rejected() {
+        let error = SerialPort::builder(SerialPortId::Usb1).device_path("/dev/tty\0USB0").build().unwrap_err();
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+
+        let error = SerialPort::builder(SerialPortId::Usb1).device_path("/dev/rbotlib-missing").build().unwrap_err();
+        assert_eq!(error.0, NO_AVAILABLE_RESOURCES);
+    }
+
+    #[test]
+    fn failed_configuration_closes_the_port() {
+        let (_master, path) = hal_stub::open_pty();
+        let error = SerialPort::builder(SerialPortId::Usb1).device_path(&path).data_bits(9).build().unwrap_err();
+
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(hal_stub::open_serial_ports(), 0);
+    }
+
+    #[test]
+    fn drop_closes_the_port() {
+        let (_master, path) = hal_stub::open_pty();
+        let serial_port = SerialPort::builder(SerialPortId::Mxp).device_path(&path).build().unwrap();
+        assert_eq!(serial_port.get_port(), SerialPortId::Mxp);
+        assert_eq!(hal_stub::open_serial_ports(), 1);
+
+        drop(serial_port);
+        assert_eq!(hal_stub::open_serial_ports(), 0);
+    }
+
+    #[test]
+    fn closed_port_is_an_io_error() {
+        let (_master, path) = hal_stub::open_pty();
+        let mut serial_port = SerialPort::builder(SerialPortId::Usb1).device_path(&path).build().unwrap();
+
+        hal_call!(HAL_CloseSerial(serial_port.handle)).unwrap();
+        assert!(serial_port.write(b"x").is_err());
+    }
+}
\ No newline at end of file
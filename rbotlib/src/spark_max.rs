@@ -0,0 +1,470 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rbothal::*;
+
+use crate::can::{self, CanDevice, CanDeviceType, CanManufacturer};
+use crate::speed_controller::SpeedController;
+
+// Api ids, each the 6 bit class followed by the 4 bit index.
+const API_DUTY_CYCLE_SET: i32 = 0x002;
+const API_VELOCITY_SET: i32 = 0x012;
+const API_POSITION_SET: i32 = 0x032;
+const API_VOLTAGE_SET: i32 = 0x042;
+const API_STATUS_0: i32 = 0x060;
+const API_STATUS_1: i32 = 0x061;
+const API_STATUS_2: i32 = 0x062;
+const API_CLEAR_FAULTS: i32 = 0x06e;
+const API_SET_FOLLOWER: i32 = 0x073;
+const API_PARAMETER_ACCESS: i32 = 0x300;
+
+// Setpoints are resent at this rate so the controller doesn't time out and disable itself.
+const SETPOINT_PERIOD: Duration = Duration::from_millis(10);
+const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+const PARAMETER_TIMEOUT: Duration = Duration::from_millis(100);
+
+// Follower config sent with the leader's id, selecting a leader that is also a SPARK MAX.
+const FOLLOWER_PREDEFINED_SPARK_MAX: u32 = 0x1a;
+const FOLLOWER_INVERTED: u32 = 1 << 18;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ControlType {
+    /// Output from -1 to 1.
+    DutyCycle,
+    /// Output in volts, compensated for the bus voltage by the controller.
+    Voltage,
+    /// Closed loop on the encoder velocity, in RPM unless a conversion factor is set.
+    Velocity,
+    /// Closed loop on the encoder position, in rotations unless a conversion factor is set.
+    Position,
+}
+
+impl ControlType {
+    fn api_id(self) -> i32 {
+        match self {
+            ControlType::DutyCycle => API_DUTY_CYCLE_SET,
+            ControlType::Voltage => API_VOLTAGE_SET,
+            ControlType::Velocity => API_VELOCITY_SET,
+            ControlType::Position => API_POSITION_SET,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Fault {
+    Brownout = 0,
+    Overcurrent = 1,
+    WatchdogReset = 2,
+    MotorFault = 3,
+    SensorFault = 4,
+    Stall = 5,
+    EepromCrc = 6,
+    CanTx = 7,
+    CanRx = 8,
+    HasReset = 9,
+    GateDriverFault = 10,
+    OtherFault = 11,
+    SoftLimitForward = 12,
+    SoftLimitReverse = 13,
+    HardLimitForward = 14,
+    HardLimitReverse = 15,
+}
+
+/// The fault bits reported in status frame 0.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Faults(pub u16);
+
+impl Faults {
+    pub fn has(self, fault: Fault) -> bool {
+        self.0 & (1 << fault as u16) != 0
+    }
+
+    pub fn any(self) -> bool {
+        self.0 != 0
+    }
+}
+
+/// Sent every 10 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status0 {
+    /// Output from -1 to 1.
+    pub applied_output: f64,
+    pub faults: Faults,
+    pub sticky_faults: Faults,
+}
+
+/// Sent every 20 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status1 {
+    pub velocity: f64,
+    /// Motor temperature in degrees Celsius.
+    pub temperature: f64,
+    pub bus_voltage: f64,
+    pub output_current: f64,
+}
+
+/// Sent every 20 ms by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Status2 {
+    pub position: f64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ParameterValue {
+    Int(i32),
+    Uint(u32),
+    Float(f32),
+    Bool(bool),
+}
+
+/// Ids of commonly used configuration parameters, for `set_parameter` and `get_parameter`.
+pub mod parameters {
+    pub const CAN_ID: u16 = 0;
+    pub const MOTOR_TYPE: u16 = 2;
+    pub const IDLE_MODE: u16 = 6;
+    pub const P_0: u16 = 13;
+    pub const I_0: u16 = 14;
+    pub const D_0: u16 = 15;
+    pub const F_0: u16 = 16;
+    pub const I_ZONE_0: u16 = 17;
+    pub const OUTPUT_MIN_0: u16 = 19;
+    pub const OUTPUT_MAX_0: u16 = 20;
+}
+
+fn encode_setpoint(value: f32, pid_slot: u8, arbitrary_feedforward: f64) -> [u8; 8] {
+    let mut data = [0; 8];
+    // The feedforward is in 1/1024 volt steps.
+    let feedforward = (arbitrary_feedforward * 1024.0).round().clamp(-32768.0, 32767.0) as i16;
+
+    data[0..4].copy_from_slice(&value.to_le_bytes());
+    data[4..6].copy_from_slice(&feedforward.to_le_bytes());
+    data[6] = pid_slot & 0x03;
+    data
+}
+
+fn decode_status_0(data: &[u8; 8]) -> Status0 {
+    Status0 {
+        applied_output: f64::from(i16::from_le_bytes([data[0], data[1]])) / 32768.0,
+        faults: Faults(u16::from_le_bytes([data[2], data[3]])),
+        sticky_faults: Faults(u16::from_le_bytes([data[4], data[5]])),
+    }
+}
+
+fn decode_status_1(data: &[u8; 8]) -> Status1 {
+    // Bus voltage and output current are packed as two 12 bit values after the temperature.
+    let bus_voltage = u16::from(data[5]) | (u16::from(data[6] & 0x0f) << 8);
+    let output_current = u16::from(data[6] >> 4) | (u16::from(data[7]) << 4);
+
+    Status1 {
+        velocity: f64::from(f32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+        temperature: f64::from(data[4]),
+        bus_voltage: f64::from(bus_voltage) / 128.0,
+        output_current: f64::from(output_current) / 32.0,
+    }
+}
+
+fn decode_status_2(data: &[u8; 8]) -> Status2 {
+    Status2 {
+        position: f64::from(f32::from_le_bytes([data[0], data[1], data[2], data[3]])),
+    }
+}
+
+fn encode_follower(leader_device_number: i32, inverted: bool) -> [u8; 8] {
+    // Followers listen for the leader's status 0 frame.
+    let leader_id = can::arbitration_id(
+        CanDeviceType::MotorController,
+        CanManufacturer::Rev,
+        API_STATUS_0,
+        leader_device_number,
+    );
+    let config = (FOLLOWER_PREDEFINED_SPARK_MAX << 24) | if inverted { FOLLOWER_INVERTED } else { 0 };
+    let mut data = [0; 8];
+
+    data[0..4].copy_from_slice(&leader_id.to_le_bytes());
+    data[4..8].copy_from_slice(&config.to_le_bytes());
+    data
+}
+
+fn encode_parameter(value: ParameterValue) -> [u8; 5] {
+    let (bits, parameter_type) = match value {
+        ParameterValue::Int(value) => (value as u32, 0),
+        ParameterValue::Uint(value) => (value, 1),
+        ParameterValue::Float(value) => (value.to_bits(), 2),
+        ParameterValue::Bool(value) => (value as u32, 3),
+    };
+    let mut data = [0; 5];
+
+    data[0..4].copy_from_slice(&bits.to_le_bytes());
+    data[4] = parameter_type;
+    data
+}
+
+fn decode_parameter(data: &[u8]) -> HalResult<ParameterValue> {
+    if data.len() < 6 || data[5] != 0 {
+        return Err(HalError(PARAMETER_OUT_OF_RANGE));
+    }
+
+    let bits = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+
+    match data[4] {
+        0 => Ok(ParameterValue::Int(bits as i32)),
+        1 => Ok(ParameterValue::Uint(bits)),
+        2 => Ok(ParameterValue::Float(f32::from_bits(bits))),
+        3 => Ok(ParameterValue::Bool(bits != 0)),
+        _ => Err(HalError(PARAMETER_OUT_OF_RANGE)),
+    }
+}
+
+/// A REV SPARK MAX controlled over CAN.
+#[derive(Debug)]
+pub struct SparkMax {
+    device: CanDevice,
+    setpoint_api: Option<i32>,
+    duty_cycle: f64,
+    inverted: bool,
+}
+
+impl SparkMax {
+    pub fn new(device_number: i32) -> HalResult<SparkMax> {
+        Ok(SparkMax {
+            device: CanDevice::new(CanManufacturer::Rev, device_number, CanDeviceType::MotorController)?,
+            setpoint_api: None,
+            duty_cycle: 0.0,
+            inverted: false,
+        })
+    }
+
+    pub fn get_device_number(&self) -> i32 {
+        self.device.get_device_number()
+    }
+
+    fn stop_setpoint(&mut self) -> HalResult<()> {
+        match self.setpoint_api.take() {
+            Some(api_id) => self.device.stop_repeating(api_id),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_reference(&mut self, value: f64, control_type: ControlType) -> HalResult<()> {
+        self.set_reference_with_feedforward(value, control_type, 0, 0.0)
+    }
+
+    /// Sets the setpoint using the gains in `pid_slot`, adding `arbitrary_feedforward` volts to
+    /// the closed loop output.
+    pub fn set_reference_with_feedforward(
+        &mut self,
+        value: f64,
+        control_type: ControlType,
+        pid_slot: u8,
+        arbitrary_feedforward: f64,
+    ) -> HalResult<()> {
+        let api_id = control_type.api_id();
+
+        // Only one setpoint may be streaming, or the controller would flip between them.
+        if self.setpoint_api != Some(api_id) {
+            self.stop_setpoint()?;
+        }
+
+        let data = encode_setpoint(value as f32, pid_slot, arbitrary_feedforward);
+        self.device.write_packet_repeating(api_id, &data, SETPOINT_PERIOD)?;
+        self.setpoint_api = Some(api_id);
+        Ok(())
+    }
+
+    fn read_status(&mut self, api_id: i32) -> HalResult<[u8; 8]> {
+        match self.device.read_timeout(api_id, STATUS_TIMEOUT)? {
+            Some(frame) => Ok(frame.data),
+            None => Err(HalError(HAL_CAN_TIMEOUT)),
+        }
+    }
+
+    pub fn get_status_0(&mut self) -> HalResult<Status0> {
+        Ok(decode_status_0(&self.read_status(API_STATUS_0)?))
+    }
+
+    pub fn get_status_1(&mut self) -> HalResult<Status1> {
+        Ok(decode_status_1(&self.read_status(API_STATUS_1)?))
+    }
+
+    pub fn get_status_2(&mut self) -> HalResult<Status2> {
+        Ok(decode_status_2(&self.read_status(API_STATUS_2)?))
+    }
+
+    pub fn get_applied_output(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_0()?.applied_output)
+    }
+
+    pub fn get_faults(&mut self) -> HalResult<Faults> {
+        Ok(self.get_status_0()?.faults)
+    }
+
+    pub fn get_sticky_faults(&mut self) -> HalResult<Faults> {
+        Ok(self.get_status_0()?.sticky_faults)
+    }
+
+    pub fn clear_faults(&mut self) -> HalResult<()> {
+        self.device.write_packet(API_CLEAR_FAULTS, &[])
+    }
+
+    pub fn get_velocity(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_1()?.velocity)
+    }
+
+    pub fn get_temperature(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_1()?.temperature)
+    }
+
+    pub fn get_bus_voltage(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_1()?.bus_voltage)
+    }
+
+    pub fn get_output_current(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_1()?.output_current)
+    }
+
+    pub fn get_position(&mut self) -> HalResult<f64> {
+        Ok(self.get_status_2()?.position)
+    }
+
+    /// Mirrors the output of the SPARK MAX with `leader_device_number`, until a setpoint is set.
+    pub fn follow(&mut self, leader_device_number: i32, inverted: bool) -> HalResult<()> {
+        self.stop_setpoint()?;
+        self.device.write_packet(API_SET_FOLLOWER, &encode_follower(leader_device_number, inverted))
+    }
+
+    pub fn set_parameter(&mut self, id: u16, value: ParameterValue) -> HalResult<()> {
+        self.device.write_packet(API_PARAMETER_ACCESS | i32::from(id), &encode_parameter(value))
+    }
+
+    pub fn get_parameter(&mut self, id: u16) -> HalResult<ParameterValue> {
+        let api_id = API_PARAMETER_ACCESS | i32::from(id);
+        let start = Instant::now();
+
+        // Throw away any stale response before asking.
+        let _ = self.device.read_new(api_id)?;
+        self.device.write_rtr_frame(api_id, 6)?;
+
+        while start.elapsed() < PARAMETER_TIMEOUT {
+            if let Some(frame) = self.device.read_new(api_id)? {
+                return decode_parameter(frame.get_data());
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        Err(HalError(HAL_CAN_TIMEOUT))
+    }
+}
+
+impl SpeedController for SparkMax {
+    fn set(&mut self, speed: f64) -> HalResult<()> {
+        self.duty_cycle = speed;
+        self.set_reference(if self.inverted { -speed } else { speed }, ControlType::DutyCycle)
+    }
+
+    fn get(&self) -> HalResult<f64> {
+        Ok(self.duty_cycle)
+    }
+
+    /// Inverts `set` and `set_voltage`, closed loop setpoints are left alone.
+    fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    fn get_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    fn disable(&mut self) -> HalResult<()> {
+        self.duty_cycle = 0.0;
+        self.stop_setpoint()?;
+        self.device.write_packet(API_DUTY_CYCLE_SET, &encode_setpoint(0.0, 0, 0.0))
+    }
+
+    /// Uses the controller's own voltage compensation instead of the roboRIO's battery reading.
+    fn set_voltage(&mut self, volts: f64) -> HalResult<()> {
+        self.set_reference(if self.inverted { -volts } else { volts }, ControlType::Voltage)
+    }
+}
+
+impl Drop for SparkMax {
+    fn drop(&mut self) {
+        let _ = self.stop_setpoint();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arbitration_ids() {
+        let id = |api_id| can::arbitration_id(CanDeviceType::MotorController, CanManufacturer::Rev, api_id, 3);
+
+        assert_eq!(id(API_DUTY_CYCLE_SET), 0x0205_0083);
+        assert_eq!(id(API_STATUS_0), 0x0205_1803);
+        assert_eq!(id(API_PARAMETER_ACCESS | i32::from(parameters::P_0)), 0x0205_c343);
+    }
+
+    #[test]
+    fn setpoint_frame() {
+        assert_eq!(encode_setpoint(0.5, 0, 0.0), [0x00, 0x00, 0x00, 0x3f, 0, 0, 0, 0]);
+        assert_eq!(encode_setpoint(-1.0, 1, 0.0), [0x00, 0x00, 0x80, 0xbf, 0, 0, 1, 0]);
+        // 1.5 V of feedforward is 1536 steps.
+        assert_eq!(encode_setpoint(1000.0, 2, 1.5), [0x00, 0x00, 0x7a, 0x44, 0x00, 0x06, 2, 0]);
+        assert_eq!(&encode_setpoint(0.0, 0, -100.0)[4..6], &i16::MIN.to_le_bytes());
+    }
+
+    #[test]
+    fn status_0_frame() {
+        let status = decode_status_0(&[0x00, 0xc0, 0x21, 0x00, 0x00, 0x02, 0, 0]);
+
+        assert_eq!(status.applied_output, -0.5);
+        assert!(status.faults.has(Fault::Brownout));
+        assert!(status.faults.has(Fault::Stall));
+        assert!(!status.faults.has(Fault::Overcurrent));
+        assert!(status.sticky_faults.has(Fault::HasReset));
+        assert!(status.sticky_faults.any());
+    }
+
+    #[test]
+    fn status_1_frame() {
+        // 1500 RPM, 40 C, 12.5 V (1600 / 128) and 20 A (640 / 32).
+        let status = decode_status_1(&[0x00, 0x80, 0xbb, 0x44, 40, 0x40, 0x06, 0x28]);
+
+        assert_eq!(
+            status,
+            Status1 {
+                velocity: 1500.0,
+                temperature: 40.0,
+                bus_voltage: 12.5,
+                output_current: 20.0,
+            }
+        );
+    }
+
+    #[test]
+    fn status_2_frame() {
+        assert_eq!(decode_status_2(&[0x00, 0x00, 0x20, 0xc1, 0, 0, 0, 0]).position, -10.0);
+    }
+
+    #[test]
+    fn follower_frame() {
+        assert_eq!(encode_follower(5, false), [0x05, 0x18, 0x05, 0x02, 0x00, 0x00, 0x00, 0x1a]);
+        assert_eq!(encode_follower(5, true), [0x05, 0x18, 0x05, 0x02, 0x00, 0x00, 0x04, 0x1a]);
+    }
+
+    #[test]
+    fn parameter_frames() {
+        assert_eq!(encode_parameter(ParameterValue::Int(-2)), [0xfe, 0xff, 0xff, 0xff, 0]);
+        assert_eq!(encode_parameter(ParameterValue::Uint(7)), [7, 0, 0, 0, 1]);
+        assert_eq!(encode_parameter(ParameterValue::Float(0.25)), [0x00, 0x00, 0x80, 0x3e, 2]);
+        assert_eq!(encode_parameter(ParameterValue::Bool(true)), [1, 0, 0, 0, 3]);
+
+        assert_eq!(decode_parameter(&[0x00, 0x00, 0x80, 0x3e, 2, 0]).unwrap(), ParameterValue::Float(0.25));
+        assert_eq!(decode_parameter(&[1, 0, 0, 0, 3, 0]).unwrap(), ParameterValue::Bool(true));
+        assert!(decode_parameter(&[1, 0, 0, 0, 3, 1]).is_err());
+        assert!(decode_parameter(&[1, 0, 0, 0, 9, 0]).is_err());
+        assert!(decode_parameter(&[1, 0, 0, 0]).is_err());
+    }
+}
\ No newline at end of file
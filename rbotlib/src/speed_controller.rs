@@ -0,0 +1,76 @@
+use rbothal::*;
+
+use crate::robot_state;
+
+pub trait SpeedController {
+    fn set(&mut self, speed: f64) -> HalResult<()>;
+
+    fn get(&self) -> HalResult<f64>;
+
+    fn set_inverted(&mut self, inverted: bool);
+
+    fn get_inverted(&self) -> bool;
+
+    fn disable(&mut self) -> HalResult<()>;
+
+    fn stop_motor(&mut self) -> HalResult<()> {
+        self.disable()
+    }
+
+    /// Sets the output as a voltage, compensating for the current battery voltage.
+    fn set_voltage(&mut self, volts: f64) -> HalResult<()> {
+        self.set(volts / robot_state::get_battery_voltage()?)
+    }
+}
+
+/// Implements `Deref`, `DerefMut` and `SpeedController` for a newtype `$name($inner)`, forwarding
+/// everything to the wrapped controller.
+macro_rules! forward_speed_controller {
+    ($name:ident, $inner:ty) => {
+        impl std::ops::Deref for $name {
+            type Target = $inner;
+
+            fn deref(&self) -> &$inner {
+                &self.0
+            }
+        }
+
+        impl std::ops::DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut $inner {
+                &mut self.0
+            }
+        }
+
+        impl $crate::speed_controller::SpeedController for $name {
+            fn set(&mut self, speed: f64) -> rbothal::HalResult<()> {
+                self.0.set(speed)
+            }
+
+            fn get(&self) -> rbothal::HalResult<f64> {
+                self.0.get()
+            }
+
+            fn set_inverted(&mut self, inverted: bool) {
+                self.0.set_inverted(inverted)
+            }
+
+            fn get_inverted(&self) -> bool {
+                self.0.get_inverted()
+            }
+
+            fn disable(&mut self) -> rbothal::HalResult<()> {
+                self.0.disable()
+            }
+
+            fn stop_motor(&mut self) -> rbothal::HalResult<()> {
+                self.0.stop_motor()
+            }
+
+            fn set_voltage(&mut self, volts: f64) -> rbothal::HalResult<()> {
+                self.0.set_voltage(volts)
+            }
+        }
+    };
+}
+
+pub(crate) use forward_speed_controller;
\ No newline at end of file
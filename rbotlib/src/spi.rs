@@ -0,0 +1,372 @@
+use rbothal::*;
+
+use crate::counter::Edge;
+use crate::dio::DigitalSource;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpiPort {
+    OnboardCs0,
+    OnboardCs1,
+    OnboardCs2,
+    OnboardCs3,
+    Mxp,
+}
+
+impl From<SpiPort> for HAL_SPIPort::Type {
+    fn from(port: SpiPort) -> HAL_SPIPort::Type {
+        match port {
+            SpiPort::OnboardCs0 => HAL_SPIPort::HAL_SPI_kOnboardCS0,
+            SpiPort::OnboardCs1 => HAL_SPIPort::HAL_SPI_kOnboardCS1,
+            SpiPort::OnboardCs2 => HAL_SPIPort::HAL_SPI_kOnboardCS2,
+            SpiPort::OnboardCs3 => HAL_SPIPort::HAL_SPI_kOnboardCS3,
+            SpiPort::Mxp => HAL_SPIPort::HAL_SPI_kMXP,
+        }
+    }
+}
+
+/// Clock polarity and phase, numbered as in most datasheets.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpiMode {
+    /// Clock idles low, data sampled on the leading edge.
+    Mode0,
+    /// Clock idles low, data sampled on the trailing edge.
+    Mode1,
+    /// Clock idles high, data sampled on the leading edge.
+    Mode2,
+    /// Clock idles high, data sampled on the trailing edge.
+    Mode3,
+}
+
+impl SpiMode {
+    fn clock_idle_high(self) -> bool {
+        self == SpiMode::Mode2 || self == SpiMode::Mode3
+    }
+
+    fn sample_on_trailing(self) -> bool {
+        self == SpiMode::Mode1 || self == SpiMode::Mode3
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpiError {
+    TransferFailed,
+}
+
+/// One transfer captured by the auto SPI engine.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AutoSpiSample {
+    /// Lower 32 bits of the FPGA time in microseconds when the transfer happened.
+    pub timestamp: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Spi {
+    port: SpiPort,
+    msb_first: bool,
+    mode: SpiMode,
+    auto_initialized: bool,
+    // Bytes per automatic transfer, unknown until `set_auto_transmit_data` is called.
+    auto_transfer_size: Option<usize>,
+    // The FPGA keeps reading from this while the auto SPI engine is running.
+    auto_trigger: Option<Box<dyn DigitalSource>>,
+}
+
+impl Spi {
+    pub fn new(port: SpiPort) -> HalResult<Spi> {
+        hal_call!(HAL_InitializeSPI(port.into()))?;
+
+        let mut spi = Spi {
+            port,
+            msb_first: true,
+            mode: SpiMode::Mode0,
+            auto_initialized: false,
+            auto_transfer_size: None,
+            auto_trigger: None,
+        };
+
+        spi.update_options();
+        Ok(spi)
+    }
+
+    pub fn get_port(&self) -> SpiPort {
+        self.port
+    }
+
+    fn update_options(&mut self) {
+        unsafe {
+            HAL_SetSPIOpts(
+                self.port.into(),
+                self.msb_first as HAL_Bool,
+                self.mode.sample_on_trailing() as HAL_Bool,
+                self.mode.clock_idle_high() as HAL_Bool,
+            );
+        }
+    }
+
+    /// Sets the clock rate in Hz, up to 4 MHz.
+    pub fn set_clock_rate(&mut self, hz: i32) {
+        unsafe {
+            HAL_SetSPISpeed(self.port.into(), hz);
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: SpiMode) {
+        self.mode = mode;
+        self.update_options();
+    }
+
+    pub fn get_mode(&self) -> SpiMode {
+        self.mode
+    }
+
+    pub fn set_msb_first(&mut self, msb_first: bool) {
+        self.msb_first = msb_first;
+        self.update_options();
+    }
+
+    pub fn get_msb_first(&self) -> bool {
+        self.msb_first
+    }
+
+    pub fn set_chip_select_active_high(&mut self, active_high: bool) -> HalResult<()> {
+        if active_high {
+            hal_call!(HAL_SetSPIChipSelectActiveHigh(self.port.into()))
+        } else {
+            hal_call!(HAL_SetSPIChipSelectActiveLow(self.port.into()))
+        }
+    }
+
+    fn check(result: i32) -> Result<usize, SpiError> {
+        if result < 0 {
+            Err(SpiError::TransferFailed)
+        } else {
+            Ok(result as usize)
+        }
+    }
+
+    /// Shifts out `send` while shifting into `receive`, returning the number of bytes transferred.
+    pub fn transaction(&mut self, send: &[u8], receive: &mut [u8]) -> Result<usize, SpiError> {
+        let size = send.len().min(receive.len());
+
+        Spi::check(unsafe { HAL_TransactionSPI(self.port.into(), send.as_ptr(), receive.as_mut_ptr(), size as i32) })
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, SpiError> {
+        Spi::check(unsafe { HAL_WriteSPI(self.port.into(), data.as_ptr(), data.len() as i32) })
+    }
+
+    /// Reads without sending anything, most devices need a register written first.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, SpiError> {
+        Spi::check(unsafe { HAL_ReadSPI(self.port.into(), buffer.as_mut_ptr(), buffer.len() as i32) })
+    }
+
+    /// Allocates the auto SPI engine with a buffer of `buffer_size` words.
+    pub fn init_auto(&mut self, buffer_size: i32) -> HalResult<()> {
+        hal_call!(HAL_InitSPIAuto(self.port.into(), buffer_size))?;
+        self.auto_initialized = true;
+        Ok(())
+    }
+
+    pub fn free_auto(&mut self) -> HalResult<()> {
+        if !self.auto_initialized {
+            return Ok(());
+        }
+
+        hal_call!(HAL_FreeSPIAuto(self.port.into()))?;
+        self.auto_initialized = false;
+        self.auto_transfer_size = None;
+        self.auto_trigger = None;
+        Ok(())
+    }
+
+    /// Sets the bytes sent on each automatic transfer, followed by `zero_size` zero bytes.
+    pub fn set_auto_transmit_data(&mut self, data: &[u8], zero_size: i32) -> HalResult<()> {
+        hal_call!(HAL_SetSPIAutoTransmitData(
+            self.port.into(),
+            data.as_ptr(),
+            data.len() as i32,
+            zero_size,
+        ))?;
+        self.auto_transfer_size = Some(data.len() + zero_size.max(0) as usize);
+        Ok(())
+    }
+
+    /// Starts transferring every `period` seconds.
+    pub fn start_auto_rate(&mut self, period: f64) -> HalResult<()> {
+        hal_call!(HAL_StartSPIAutoRate(self.port.into(), period))
+    }
+
+    /// Starts transferring on edges of `source`, such as a data ready pin.
+    pub fn start_auto_trigger<S>(&mut self, source: S, edge: Edge) -> HalResult<()>
+    where
+        S: DigitalSource + 'static,
+    {
+        hal_call!(HAL_StartSPIAutoTrigger(
+            self.port.into(),
+            source.get_port_handle_for_routing(),
+            source.get_analog_trigger_type_for_routing(),
+            edge.rising(),
+            edge.falling(),
+        ))?;
+        self.auto_trigger = Some(Box::new(source));
+        Ok(())
+    }
+
+    pub fn stop_auto(&mut self) -> HalResult<()> {
+        hal_call!(HAL_StopSPIAuto(self.port.into()))?;
+        self.auto_trigger = None;
+        Ok(())
+    }
+
+    pub fn force_auto_read(&mut self) -> HalResult<()> {
+        hal_call!(HAL_ForceSPIAutoRead(self.port.into()))
+    }
+
+    /// Reads raw words from the auto SPI buffer, each transfer being a timestamp followed by one
+    /// word per byte. Waits up to `timeout` seconds for enough words to fill `buffer`, and
+    /// returns the number of words still waiting after the read, as the HAL does.
+    pub fn read_auto_received_data(&mut self, buffer: &mut [u32], timeout: f64) -> HalResult<usize> {
+        let remaining = hal_call!(HAL_ReadSPIAutoReceivedData(
+            self.port.into(),
+            buffer.as_mut_ptr(),
+            buffer.len() as i32,
+            timeout,
+        ))?;
+
+        Ok(remaining as usize)
+    }
+
+    /// Gets the number of words waiting in the auto SPI buffer.
+    pub fn get_auto_available(&self) -> HalResult<usize> {
+        let available = hal_call!(HAL_ReadSPIAutoReceivedData(
+            self.port.into(),
+            std::ptr::null_mut(),
+            0,
+            0.0,
+        ))?;
+
+        Ok(available as usize)
+    }
+
+    /// Reads up to `max_count` complete transfers already in the auto SPI buffer, leaving the
+    /// words of a transfer still in progress for the next call. Fails unless
+    /// `set_auto_transmit_data` was called, since the transfer size isn't known otherwise.
+    pub fn read_auto_samples(&mut self, max_count: usize) -> HalResult<Vec<AutoSpiSample>> {
+        let words_per_sample = match self.auto_transfer_size {
+            Some(size) => size + 1,
+            None => return Err(HalError(INCOMPATIBLE_STATE)),
+        };
+        let available = self.get_auto_available()?;
+        let count = (available / words_per_sample).min(max_count);
+        let mut buffer = vec![0; count * words_per_sample];
+
+        self.read_auto_received_data(&mut buffer, 0.0)?;
+
+        Ok(buffer
+            .chunks(words_per_sample)
+            .map(|words| AutoSpiSample {
+                timestamp: words[0],
+                data: words[1..].iter().map(|&word| word as u8).collect(),
+            })
+            .collect())
+    }
+
+    /// Gets the number of transfers dropped because the buffer was full.
+    pub fn get_auto_dropped_count(&self) -> HalResult<i32> {
+        hal_call!(HAL_GetSPIAutoDroppedCount(self.port.into()))
+    }
+}
+
+impl Drop for Spi {
+    fn drop(&mut self) {
+        let _ = self.free_auto();
+
+        unsafe {
+            HAL_CloseSPI(self.port.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+
+    const PORT: HAL_SPIPort::Type = HAL_SPIPort::HAL_SPI_kOnboardCS0;
+
+    #[test]
+    fn mode_sets_clock_options() {
+        let mut spi = Spi::new(SpiPort::OnboardCs0).unwrap();
+        let state = hal_stub::spi(PORT);
+        assert!(state.msb_first && !state.sample_on_trailing && !state.clock_idle_high);
+
+        spi.set_mode(SpiMode::Mode3);
+        spi.set_msb_first(false);
+        let state = hal_stub::spi(PORT);
+        assert!(!state.msb_first && state.sample_on_trailing && state.clock_idle_high);
+
+        spi.set_mode(SpiMode::Mode2);
+        assert!(!hal_stub::spi(PORT).sample_on_trailing && hal_stub::spi(PORT).clock_idle_high);
+    }
+
+    #[test]
+    fn auto_samples_need_the_transfer_size() {
+        let mut spi = Spi::new(SpiPort::OnboardCs0).unwrap();
+        spi.init_auto(64).unwrap();
+        hal_stub::push_spi_auto_transfer(PORT, 1, &[2, 3]);
+
+        assert_eq!(spi.read_auto_samples(1).unwrap_err().0, INCOMPATIBLE_STATE);
+
+        spi.set_auto_transmit_data(&[0x80], 1).unwrap();
+        spi.free_auto().unwrap();
+        spi.init_auto(64).unwrap();
+        assert_eq!(spi.read_auto_samples(1).unwrap_err().0, INCOMPATIBLE_STATE);
+    }
+
+    #[test]
+    fn auto_samples_decode_timestamps_and_data() {
+        let mut spi = Spi::new(SpiPort::OnboardCs0).unwrap();
+        spi.init_auto(64).unwrap();
+        spi.set_auto_transmit_data(&[0x80], 1).unwrap();
+        spi.start_auto_rate(0.001).unwrap();
+        let auto = hal_stub::spi(PORT).auto.unwrap();
+        assert_eq!((auto.buffer_size, auto.transmit_data, auto.zero_size), (64, vec![0x80], 1));
+        assert_eq!(auto.period, Some(0.001));
+
+        hal_stub::push_spi_auto_transfer(PORT, 1000, &[0x12, 0x34]);
+        hal_stub::push_spi_auto_transfer(PORT, 2000, &[0x56, 0x78]);
+        hal_stub::push_spi_auto_transfer(PORT, 3000, &[0x9a, 0xbc]);
+        // The first words of a fourth transfer, still being shifted in.
+        hal_stub::push_spi_auto_transfer(PORT, 4000, &[0xde]);
+        assert_eq!(spi.get_auto_available().unwrap(), 11);
+
+        let sample = |timestamp, data: &[u8]| AutoSpiSample {
+            timestamp,
+            data: data.to_vec(),
+        };
+
+        assert_eq!(spi.read_auto_samples(1).unwrap(), [sample(1000, &[0x12, 0x34])]);
+        assert_eq!(
+            spi.read_auto_samples(10).unwrap(),
+            [sample(2000, &[0x56, 0x78]), sample(3000, &[0x9a, 0xbc])]
+        );
+        assert_eq!(spi.get_auto_available().unwrap(), 2);
+        assert_eq!(spi.read_auto_samples(10).unwrap(), []);
+
+        let mut words = [0; 1];
+        assert_eq!(spi.read_auto_received_data(&mut words, 0.0).unwrap(), 1);
+        assert_eq!(words, [4000]);
+        assert_eq!(spi.read_auto_received_data(&mut [0; 2], 0.0).unwrap_err().0, hal_stub::NIFPGA_FIFO_TIMEOUT);
+    }
+
+    #[test]
+    fn drop_frees_auto_and_closes() {
+        let mut spi = Spi::new(SpiPort::Mxp).unwrap();
+        spi.init_auto(64).unwrap();
+
+        drop(spi);
+        let state = hal_stub::spi(HAL_SPIPort::HAL_SPI_kMXP);
+        assert!(state.auto.is_none() && state.closed);
+    }
+}
\ No newline at end of file
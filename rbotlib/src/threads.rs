@@ -0,0 +1,161 @@
+use std::os::unix::thread::JoinHandleExt;
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+
+use rbothal::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ThreadPriority {
+    /// The default time-shared scheduling.
+    Normal,
+    /// Real-time scheduling from 1 to 99, preempting normal threads and lower real-time ones.
+    RealTime(i32),
+}
+
+impl ThreadPriority {
+    fn to_hal(self) -> HalResult<(HAL_Bool, i32)> {
+        match self {
+            ThreadPriority::Normal => Ok((0, 0)),
+            ThreadPriority::RealTime(priority) if (1..=99).contains(&priority) => Ok((1, priority)),
+            ThreadPriority::RealTime(_) => Err(HalError(PARAMETER_OUT_OF_RANGE)),
+        }
+    }
+
+    fn from_hal(real_time: HAL_Bool, priority: i32) -> ThreadPriority {
+        if real_time != 0 {
+            ThreadPriority::RealTime(priority)
+        } else {
+            ThreadPriority::Normal
+        }
+    }
+}
+
+fn check_set(success: HAL_Bool) -> HalResult<()> {
+    if success == 0 {
+        Err(HalError(PARAMETER_OUT_OF_RANGE))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn get_current_thread_priority() -> HalResult<ThreadPriority> {
+    let mut real_time = 0;
+    let priority = hal_call!(HAL_GetCurrentThreadPriority(&mut real_time))?;
+
+    Ok(ThreadPriority::from_hal(real_time, priority))
+}
+
+pub fn set_current_thread_priority(priority: ThreadPriority) -> HalResult<()> {
+    let (real_time, priority) = priority.to_hal()?;
+
+    check_set(hal_call!(HAL_SetCurrentThreadPriority(real_time, priority))?)
+}
+
+pub fn get_thread_priority<T>(thread: &JoinHandle<T>) -> HalResult<ThreadPriority> {
+    let pthread = thread.as_pthread_t();
+    let mut real_time = 0;
+    let priority = hal_call!(HAL_GetThreadPriority(
+        &pthread as *const _ as NativeThreadHandle,
+        &mut real_time,
+    ))?;
+
+    Ok(ThreadPriority::from_hal(real_time, priority))
+}
+
+pub fn set_thread_priority<T>(thread: &JoinHandle<T>, priority: ThreadPriority) -> HalResult<()> {
+    let pthread = thread.as_pthread_t();
+    let (real_time, priority) = priority.to_hal()?;
+
+    check_set(hal_call!(HAL_SetThreadPriority(
+        &pthread as *const _ as NativeThreadHandle,
+        real_time,
+        priority,
+    ))?)
+}
+
+/// Spawns a named thread that sets its own priority before running `f`, so none of `f` runs at the
+/// wrong priority. If the priority can't be set the thread exits without running `f` and the
+/// error is returned.
+pub fn spawn_with_priority<F, T>(name: &str, priority: ThreadPriority, f: F) -> HalResult<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    // The builder panics on these instead of returning an error.
+    if name.contains('\0') {
+        return Err(HalError(PARAMETER_OUT_OF_RANGE));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    let thread = thread::Builder::new()
+        .name(name.to_owned())
+        .spawn(move || {
+            let result = set_current_thread_priority(priority);
+            let _ = sender.send(result);
+
+            if let Err(error) = result {
+                // Unwinds without running the panic hook, the error is reported to the spawner.
+                std::panic::resume_unwind(Box::new(error));
+            }
+
+            f()
+        })
+        .map_err(|_| HalError(NO_AVAILABLE_RESOURCES))?;
+
+    match receiver.recv() {
+        Ok(Ok(())) => Ok(thread),
+        Ok(Err(error)) => Err(error),
+        Err(_) => Err(HalError(NO_AVAILABLE_RESOURCES)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hal_stub;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn spawned_thread_runs_at_its_priority() {
+        let thread = spawn_with_priority("control", ThreadPriority::RealTime(20), || {
+            (thread::current().name().map(str::to_owned), get_current_thread_priority().unwrap())
+        })
+        .unwrap();
+
+        let (name, priority) = thread.join().unwrap();
+        assert_eq!(name.as_deref(), Some("control"));
+        assert_eq!(priority, ThreadPriority::RealTime(20));
+        assert_eq!(get_current_thread_priority().unwrap(), ThreadPriority::Normal);
+    }
+
+    #[test]
+    fn invalid_priority_is_rejected() {
+        assert_eq!(set_current_thread_priority(ThreadPriority::RealTime(0)).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(set_current_thread_priority(ThreadPriority::RealTime(100)).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+
+        let refused = ThreadPriority::RealTime(hal_stub::MAX_REAL_TIME_PRIORITY + 1);
+        assert_eq!(set_current_thread_priority(refused).unwrap_err().0, PARAMETER_OUT_OF_RANGE);
+        assert_eq!(get_current_thread_priority().unwrap(), ThreadPriority::Normal);
+    }
+
+    #[test]
+    fn spawn_fails_without_running_when_priority_is_refused() {
+        let ran = Arc::new(AtomicBool::new(false));
+        let thread_ran = Arc::clone(&ran);
+        let priority = ThreadPriority::RealTime(hal_stub::MAX_REAL_TIME_PRIORITY + 1);
+
+        let result = spawn_with_priority("logger", priority, move || thread_ran.store(true, Ordering::SeqCst));
+        let error = result.unwrap_err();
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn name_with_nul_is_rejected() {
+        let error = spawn_with_priority("log\0ger", ThreadPriority::Normal, || ()).unwrap_err();
+
+        assert_eq!(error.0, PARAMETER_OUT_OF_RANGE);
+    }
+}
\ No newline at end of file